@@ -0,0 +1,95 @@
+use crate::level::LevelProgression;
+use crate::mode::Mode;
+use crate::randomizer::RandomizerKind;
+
+// what gives a piece laying on the ground more time before it locks
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LockReset {
+    // moving or rotating resets lock delay, up to `lock_reset_limit` times per piece
+    Move,
+    // only falling to a new lowest row resets lock delay, classic rules
+    Step,
+}
+
+// rules chosen before a game starts
+#[derive(Clone, Copy, Debug)]
+pub struct Config {
+    pub mode: Mode,
+    // marathon goes on past its goal and level cap until top out
+    pub endless: bool,
+    // garbage rows to clear in dig mode, one of DIG_ROWS
+    pub dig_rows: u32,
+    pub randomizer: RandomizerKind,
+    // board size in cells, without hidden rows
+    pub width: usize,
+    pub height: usize,
+    // how many upcoming pieces are shown, 1 to MAX_NEXT_COUNT
+    pub next_count: usize,
+    // how long a piece can lay on the ground before it locks
+    pub lock_delay_ms: u32,
+    pub lock_reset: LockReset,
+    pub lock_reset_limit: u32,
+    // delayed auto shift, how long left or right must be held before piece starts moving by itself
+    pub das_ms: u32,
+    // auto repeat rate, time between moves after das, 0 moves to the wall instantly
+    pub arr_ms: u32,
+    // how many times gravity is faster while soft drop is held
+    pub soft_drop_factor: u32,
+    pub start_level: i32,
+    pub level_progression: LevelProgression,
+    // end the game when a piece locks even partly above the visible rows
+    pub partial_lock_out: bool,
+    // READY and GO countdown before the first piece
+    pub ready_ms: u32,
+    pub go_ms: u32,
+    // entry delay, time between a piece locking or lines vanishing and the next piece appearing
+    pub are_ms: u32,
+    // how long filled lines stay visible before they are removed
+    pub line_clear_ms: u32,
+}
+
+pub const MAX_NEXT_COUNT: usize = 6;
+
+impl Config {
+    // lines that finish the game, None to play until top out
+    pub fn goal_lines(&self) -> Option<i32> {
+        match self.mode {
+            Mode::Marathon if self.endless => None,
+            mode => mode.goal_lines(),
+        }
+    }
+
+    pub fn max_level(&self) -> Option<i32> {
+        match self.mode {
+            Mode::Marathon if self.endless => None,
+            mode => mode.max_level(),
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            mode: Mode::Marathon,
+            endless: false,
+            dig_rows: 10,
+            randomizer: RandomizerKind::SevenBag,
+            width: 10,
+            height: 20,
+            next_count: 5,
+            lock_delay_ms: 500,
+            lock_reset: LockReset::Move,
+            lock_reset_limit: 15,
+            das_ms: 167,
+            arr_ms: 33,
+            soft_drop_factor: 20,
+            start_level: 1,
+            level_progression: LevelProgression::EveryTenLines,
+            partial_lock_out: false,
+            ready_ms: 800,
+            go_ms: 800,
+            are_ms: 100,
+            line_clear_ms: 400,
+        }
+    }
+}
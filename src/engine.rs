@@ -0,0 +1,1131 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::config::{Config, LockReset, MAX_NEXT_COUNT};
+use crate::field::GameField;
+use crate::input::{Button, Controller, Input, SHIFT_TO_WALL};
+use crate::level::{guideline_gravity, Leveling, MAX_GRAVITY};
+use crate::master::{master_delays, master_gravity, Delays, Grading, MASTER_MAX_LEVEL, SECTION_LEVELS};
+use crate::mode::{Mode, DIG_BOARD_ROWS};
+use crate::piece::{Piece, PieceKind};
+use crate::piece_set::PieceSet;
+use crate::randomizer::Randomizer;
+use crate::scoring::{ClearStats, LineClear, Scoring, TSpin};
+
+// simulation ticks per second, independent of how often the frontend renders
+pub const TICKRATE: u32 = 60;
+pub const TICK: Duration = Duration::from_nanos(1_000_000_000 / TICKRATE as u64);
+
+// how long tetris, T-spin or perfect clear callout stays on screen
+const CALLOUT_TICKS: u32 = 90;
+
+fn ms_to_ticks(ms: u32) -> u32 {
+    (ms * TICKRATE + 500) / 1000
+}
+
+fn ticks_to_millis(ticks: u64) -> u64 {
+    ticks * 1000 / TICKRATE as u64
+}
+
+// reasons the game ends
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TopOut {
+    // new piece spawned overlapping laying pieces
+    BlockOut,
+    // piece locked entirely above the visible rows
+    LockOut,
+    // piece locked partly above the visible rows, only with `Config::partial_lock_out`
+    PartialLockOut,
+}
+
+impl TopOut {
+    pub fn name(self) -> &'static str {
+        match self {
+            TopOut::BlockOut => "block out",
+            TopOut::LockOut => "lock out",
+            TopOut::PartialLockOut => "partial lock out",
+        }
+    }
+}
+
+// what the game is doing between pieces
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Phase {
+    // countdown before the first piece, the clock does not run yet
+    Ready,
+    Go,
+    // piece falls and can be controlled
+    Falling,
+    // filled lines are shown before they are removed
+    LineClear,
+    // entry delay before the next piece appears
+    Entry,
+}
+
+// headless game rules, one `step` is one fixed simulation tick of `TICK` length
+pub struct Engine {
+    config: Config,
+    piece_set: PieceSet,
+    randomizer: Box<dyn Randomizer>,
+    controller: Controller,
+    field: GameField,
+    phase: Phase,
+    // ticks left in the current phase, unused while falling
+    phase_ticks: u32,
+    piece: Piece,
+    next_pieces: VecDeque<PieceKind>,
+    hold_piece: Option<PieceKind>,
+    // hold can be used once per piece, reset when piece is placed
+    hold_used: bool,
+    // part of a row the piece has fallen, piece moves down when it reaches 1
+    fall_progress: f64,
+    // ticks current piece spent on the ground
+    lock_ticks: u32,
+    lock_resets: u32,
+    // lowest row a cell of the current piece reached, getting below it resets lock delay
+    lowest_y: i32,
+    scoring: Scoring,
+    clear_stats: ClearStats,
+    leveling: Leveling,
+    // last notable clear and for how many more ticks to show it
+    callout: Option<LineClear>,
+    callout_ticks: u32,
+    lines: i32,
+    ticks: u64,
+    // pieces locked so far
+    pieces: u32,
+    // garbage rows of dig mode that are not on the board yet
+    garbage_left: u32,
+    garbage_rng: StdRng,
+    // ticks at which every split of the mode was reached
+    splits: Vec<u64>,
+    // hidden grade of master mode and the tick its current section started
+    grading: Grading,
+    section_start: u64,
+    // goal of the mode reached
+    finished: bool,
+    top_out: Option<TopOut>,
+}
+
+impl Engine {
+    // game with the guideline tetrominoes
+    pub fn new(config: Config) -> Engine {
+        Engine::with_piece_set(config, PieceSet::tetromino())
+    }
+
+    pub fn with_piece_set(config: Config, piece_set: PieceSet) -> Engine {
+        let mut randomizer = config.randomizer.create(&piece_set);
+        let field = GameField::new(config.width, config.height);
+        let next_count = config.next_count.clamp(1, MAX_NEXT_COUNT);
+        let next_pieces: VecDeque<PieceKind> = (0..next_count).map(|_| randomizer.next()).collect();
+        // not in play until the countdown ends
+        let piece = Piece::spawn(&piece_set, next_pieces[0], &field);
+        let lowest_y = piece.bottom();
+        let mut engine = Engine {
+            config,
+            piece_set,
+            randomizer,
+            controller: Controller::default(),
+            field,
+            phase: Phase::Ready,
+            phase_ticks: 0,
+            piece,
+            next_pieces,
+            hold_piece: None,
+            hold_used: false,
+            fall_progress: 0.,
+            lock_ticks: 0,
+            lock_resets: 0,
+            lowest_y,
+            scoring: Scoring::new(),
+            clear_stats: ClearStats::default(),
+            leveling: Leveling::new(
+                config.mode.fixed_progression().unwrap_or(config.level_progression),
+                config.start_level,
+                config.max_level(),
+            ),
+            callout: None,
+            callout_ticks: 0,
+            lines: 0,
+            ticks: 0,
+            pieces: 0,
+            garbage_left: match config.mode {
+                Mode::Dig => config.dig_rows,
+                _ => 0,
+            },
+            garbage_rng: StdRng::from_entropy(),
+            splits: Vec::new(),
+            grading: Grading::new(),
+            section_start: 0,
+            finished: false,
+            top_out: None,
+        };
+        engine.feed_garbage();
+        engine.start_phase(Phase::Ready, ms_to_ticks(config.ready_ms));
+        engine
+    }
+
+    pub fn restart(&mut self) {
+        *self = Engine::with_piece_set(self.config, self.piece_set.clone());
+    }
+
+    pub fn step(&mut self, inputs: &[Input]) {
+        if self.is_game_over() {
+            return;
+        }
+
+        // a piece locked by hard drop starts its delay on the next tick
+        let delayed = self.phase != Phase::Falling;
+        for &input in inputs {
+            match input {
+                Input::Press(button) => {
+                    self.controller.press(button);
+                    if self.phase == Phase::Falling {
+                        self.on_press(button);
+                    }
+                },
+                Input::Release(button) => self.controller.release(button),
+            }
+            if self.is_game_over() {
+                return;
+            }
+        }
+
+        let counting_down = matches!(self.phase, Phase::Ready | Phase::Go);
+        if delayed {
+            self.update_delay();
+        } else if self.phase == Phase::Falling {
+            self.update_piece();
+        }
+
+        if self.callout_ticks > 0 {
+            self.callout_ticks -= 1;
+            if self.callout_ticks == 0 {
+                self.callout = None;
+            }
+        }
+
+        if !counting_down {
+            self.ticks += 1;
+        }
+        if self.remaining_millis() == Some(0) {
+            self.finished = true;
+        }
+    }
+
+    // shifting, gravity and locking of the falling piece
+    fn update_piece(&mut self) {
+        let direction = self.controller.shift_direction();
+        let mut shifts = self.controller.auto_shift(self.delays().das, ms_to_ticks(self.config.arr_ms));
+        while shifts > 0 && self.piece.move_x(direction, &self.field) {
+            self.on_piece_moved();
+            if shifts != SHIFT_TO_WALL {
+                shifts -= 1;
+            }
+        }
+
+        let soft_drop = self.controller.is_held(Button::SoftDrop);
+        self.fall_progress += match soft_drop {
+            true => self.gravity() * self.config.soft_drop_factor.max(1) as f64,
+            false => self.gravity(),
+        };
+        while self.fall_progress >= 1. {
+            if !self.piece.is_move_down_awailable(&self.field) {
+                self.fall_progress = 0.;
+                break;
+            }
+            self.piece.force_move_y(1);
+            self.fall_progress -= 1.;
+            if soft_drop {
+                self.scoring.soft_drop(1);
+            }
+        }
+
+        if self.config.mode == Mode::Master && self.scoring.combo() < 1 {
+            self.grading.decay();
+        }
+
+        if self.piece.bottom() > self.lowest_y {
+            self.lowest_y = self.piece.bottom();
+            self.lock_ticks = 0;
+            self.lock_resets = 0;
+        }
+
+        // place piece if it lays on the ground long enough,
+        // lock delay is paused while piece is in the air
+        if !self.piece.is_move_down_awailable(&self.field) {
+            self.lock_ticks += 1;
+            let out_of_resets = self.lock_reset() == LockReset::Move && self.lock_resets >= self.config.lock_reset_limit;
+            if self.lock_ticks >= self.lock_delay_ticks() || out_of_resets {
+                self.lock_piece();
+            }
+        }
+    }
+
+    // countdown of a phase without a piece, auto shift keeps charging
+    fn update_delay(&mut self) {
+        self.controller.auto_shift(self.delays().das, ms_to_ticks(self.config.arr_ms));
+        self.phase_ticks = self.phase_ticks.saturating_sub(1);
+        if self.phase_ticks == 0 {
+            self.end_phase();
+        }
+    }
+
+    fn start_phase(&mut self, phase: Phase, ticks: u32) {
+        self.phase = phase;
+        self.phase_ticks = ticks;
+        if self.phase_ticks == 0 {
+            self.end_phase();
+        }
+    }
+
+    fn end_phase(&mut self) {
+        match self.phase {
+            Phase::Ready => self.start_phase(Phase::Go, ms_to_ticks(self.config.go_ms)),
+            Phase::Go => self.enter_next(),
+            Phase::Falling => (),
+            Phase::LineClear => {
+                self.field.remove_filled_lines();
+                self.feed_garbage();
+                if self.config.mode == Mode::Dig && self.garbage_remaining() == 0 {
+                    self.finished = true;
+                    return;
+                }
+                self.start_phase(Phase::Entry, self.delays().line_are);
+            },
+            Phase::Entry => self.enter_next(),
+        }
+    }
+
+    // garbage rows come up from below until the board has enough of them
+    fn feed_garbage(&mut self) {
+        let board_rows = DIG_BOARD_ROWS.min(self.field.visible_height() / 2);
+        while self.garbage_left > 0 && self.field.garbage_rows() < board_rows {
+            let hole = self.garbage_rng.gen_range(0..self.field.width());
+            let row = self.field.garbage_row(hole);
+            self.field.push_row(row);
+            self.garbage_left -= 1;
+        }
+    }
+
+    fn on_press(&mut self, button: Button) {
+        let moved = match button {
+            Button::Left => self.piece.move_x(-1, &self.field),
+            Button::Right => self.piece.move_x(1, &self.field),
+            Button::RotateLeft => self.piece.rotate(&self.field, -1),
+            Button::RotateRight => self.piece.rotate(&self.field, 1),
+            Button::SoftDrop => false,
+            Button::HardDrop => {
+                let from_y = self.piece.y;
+                self.piece.drop_down(&self.field);
+                self.scoring.hard_drop(self.piece.y - from_y);
+                self.lock_piece();
+                false
+            },
+            Button::Hold => {
+                self.hold();
+                false
+            },
+        };
+        if moved {
+            self.on_piece_moved();
+        }
+    }
+
+    // successful move or rotation, may give more time before lock
+    fn on_piece_moved(&mut self) {
+        if self.lock_reset() == LockReset::Move && self.lock_resets < self.config.lock_reset_limit {
+            self.lock_ticks = 0;
+            self.lock_resets += 1;
+        }
+        self.drop_at_max_gravity();
+    }
+
+    // gravity moves a limited number of rows per tick, at 20G the piece goes all the way down
+    // so it can't slide over holes or float on tall boards
+    fn drop_at_max_gravity(&mut self) {
+        if self.gravity() >= MAX_GRAVITY {
+            self.piece.drop_down(&self.field);
+        }
+    }
+
+    fn lock_piece(&mut self) {
+        self.pieces += 1;
+        let t_spin = self.piece.t_spin(&self.field);
+        self.piece.put_on_a_field(&mut self.field);
+
+        let cells = self.piece.cells();
+        let hidden_cells = cells.iter().filter(|&&(_, y)| y < self.field.visible_top()).count();
+        if hidden_cells == cells.len() {
+            self.top_out = Some(TopOut::LockOut);
+            return;
+        }
+        if hidden_cells > 0 && self.config.partial_lock_out {
+            self.top_out = Some(TopOut::PartialLockOut);
+            return;
+        }
+
+        // filled lines stay on the field until the line clear delay ends
+        let filled_lines = self.field.filled_lines().len() as i32;
+        let clear = LineClear {
+            lines: filled_lines,
+            t_spin,
+            perfect_clear: filled_lines > 0 && self.field.is_empty_without_filled_lines(),
+        };
+        let back_to_back = clear.is_difficult() && self.scoring.is_back_to_back();
+        let multiplier = self.config.mode.fixed_score_multiplier(self.level()).unwrap_or(self.level());
+        self.scoring.line_clear(clear, multiplier);
+        self.clear_stats.add(clear);
+        let section = self.level() / SECTION_LEVELS;
+        self.leveling.line_clear(clear, back_to_back);
+        self.lines += filled_lines;
+        if self.config.mode == Mode::Master {
+            self.grading.line_clear(filled_lines, self.scoring.combo(), self.level());
+            if self.level() / SECTION_LEVELS > section || self.level() >= MASTER_MAX_LEVEL {
+                self.grading.section(self.ticks - self.section_start);
+                self.section_start = self.ticks;
+            }
+            if self.level() >= MASTER_MAX_LEVEL {
+                self.finished = true;
+                return;
+            }
+        }
+        if let Some(split_lines) = self.config.mode.split_lines() {
+            while (self.splits.len() as i32 + 1) * split_lines <= self.lines {
+                self.splits.push(self.ticks);
+            }
+        }
+        if self.config.goal_lines().is_some_and(|goal| self.lines >= goal) {
+            self.finished = true;
+            return;
+        }
+        if clear.is_difficult() || t_spin != TSpin::None || clear.perfect_clear {
+            self.callout = Some(clear);
+            self.callout_ticks = CALLOUT_TICKS;
+        }
+
+        self.hold_used = false;
+        let delays = self.delays();
+        match filled_lines {
+            0 => self.start_phase(Phase::Entry, delays.are),
+            _ => self.start_phase(Phase::LineClear, delays.line_clear),
+        }
+    }
+
+    // new piece appears above the visible rows, rotated if a rotation button is held,
+    // and drops one row at once if it can
+    fn spawn(&mut self, kind: PieceKind) {
+        self.piece = Piece::spawn(&self.piece_set, kind, &self.field);
+        let rotation = match (self.controller.is_held(Button::RotateLeft), self.controller.is_held(Button::RotateRight)) {
+            (true, false) => -1,
+            (false, true) => 1,
+            _ => 0,
+        };
+        if rotation != 0 {
+            self.piece.rotate(&self.field, rotation);
+            self.piece.last_kick = None;
+        }
+        self.phase = Phase::Falling;
+        if !self.piece.fits(&self.field) {
+            self.top_out = Some(TopOut::BlockOut);
+            return;
+        }
+        if self.piece.is_move_down_awailable(&self.field) {
+            self.piece.force_move_y(1);
+        }
+        self.drop_at_max_gravity();
+        self.fall_progress = 0.;
+        self.lock_ticks = 0;
+        self.lock_resets = 0;
+        self.lowest_y = self.piece.bottom();
+    }
+
+    fn spawn_next(&mut self) {
+        self.next_pieces.push_back(self.randomizer.next());
+        let kind = self.next_pieces.pop_front().unwrap();
+        self.spawn(kind);
+    }
+
+    // piece after a delay, initial hold if hold is held as it appears
+    fn enter_next(&mut self) {
+        if !self.controller.is_held(Button::Hold) || self.hold_used {
+            self.spawn_next();
+            return;
+        }
+        self.next_pieces.push_back(self.randomizer.next());
+        let kind = self.next_pieces.pop_front().unwrap();
+        self.hold_used = true;
+        match self.hold_piece.replace(kind) {
+            Some(held) => self.spawn(held),
+            None => self.spawn_next(),
+        }
+    }
+
+    fn hold(&mut self) {
+        if self.hold_used {
+            return;
+        }
+        match self.hold_piece.replace(self.piece.kind) {
+            Some(kind) => self.spawn(kind),
+            None => self.spawn_next(),
+        }
+        self.hold_used = true;
+    }
+
+    fn lock_reset(&self) -> LockReset {
+        self.config.mode.fixed_lock_reset().unwrap_or(self.config.lock_reset)
+    }
+
+    fn lock_delay_ticks(&self) -> u32 {
+        self.delays().lock.max(1)
+    }
+
+    // delays in ticks, master mode shortens them in later sections
+    pub fn delays(&self) -> Delays {
+        if self.config.mode == Mode::Master {
+            return master_delays(self.level());
+        }
+        let are = ms_to_ticks(self.config.are_ms);
+        Delays {
+            are,
+            line_are: are,
+            das: ms_to_ticks(self.config.das_ms),
+            lock: ms_to_ticks(self.config.lock_delay_ms),
+            line_clear: ms_to_ticks(self.config.line_clear_ms),
+        }
+    }
+
+    // rows per tick
+    pub fn gravity(&self) -> f64 {
+        match self.config.mode {
+            Mode::Master => master_gravity(self.level()),
+            mode => mode.fixed_gravity().unwrap_or_else(|| guideline_gravity(self.level())),
+        }
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn field(&self) -> &GameField {
+        &self.field
+    }
+
+    pub fn piece_set(&self) -> &PieceSet {
+        &self.piece_set
+    }
+
+    pub fn phase(&self) -> Phase {
+        self.phase
+    }
+
+    // filled lines shown during line clear delay
+    pub fn clearing_lines(&self) -> Vec<usize> {
+        match self.phase {
+            Phase::LineClear => self.field.filled_lines(),
+            _ => Vec::new(),
+        }
+    }
+
+    // current piece, only in play while the phase is Falling
+    pub fn piece(&self) -> &Piece {
+        &self.piece
+    }
+
+    // current piece moved down to where it would land
+    pub fn ghost_piece(&self) -> Piece {
+        let mut ghost = self.piece.clone();
+        ghost.drop_down(&self.field);
+        ghost
+    }
+
+    // upcoming pieces, the first one spawns next
+    pub fn next_pieces(&self) -> &VecDeque<PieceKind> {
+        &self.next_pieces
+    }
+
+    pub fn hold_piece(&self) -> Option<PieceKind> {
+        self.hold_piece
+    }
+
+    // false if hold was already used for the current piece
+    pub fn can_hold(&self) -> bool {
+        !self.hold_used
+    }
+
+    pub fn level(&self) -> i32 {
+        self.leveling.level()
+    }
+
+    pub fn leveling(&self) -> &Leveling {
+        &self.leveling
+    }
+
+    pub fn score(&self) -> i32 {
+        self.scoring.score()
+    }
+
+    pub fn scoring(&self) -> &Scoring {
+        &self.scoring
+    }
+
+    pub fn clear_stats(&self) -> &ClearStats {
+        &self.clear_stats
+    }
+
+    // recent tetris, T-spin or perfect clear to show on screen
+    pub fn callout(&self) -> Option<LineClear> {
+        self.callout
+    }
+
+    pub fn lines(&self) -> i32 {
+        self.lines
+    }
+
+    // ticks played since the countdown ended
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    pub fn seconds(&self) -> f64 {
+        self.ticks as f64 / TICKRATE as f64
+    }
+
+    pub fn millis(&self) -> u64 {
+        ticks_to_millis(self.ticks)
+    }
+
+    pub fn pieces(&self) -> u32 {
+        self.pieces
+    }
+
+    // garbage rows on the board and still to come in dig mode
+    pub fn garbage_remaining(&self) -> u32 {
+        self.garbage_left + self.field.garbage_rows() as u32
+    }
+
+    // time left in modes with a time limit
+    pub fn remaining_millis(&self) -> Option<u64> {
+        self.config.mode.time_limit_ms().map(|limit| limit.saturating_sub(self.millis()))
+    }
+
+    // times in milliseconds at which the splits of the mode were reached
+    pub fn split_millis(&self) -> Vec<u64> {
+        self.splits.iter().map(|&ticks| ticks_to_millis(ticks)).collect()
+    }
+
+    // hidden grade of master mode, only final once the game is over
+    pub fn grade(&self) -> Option<&'static str> {
+        match self.config.mode {
+            Mode::Master => Some(self.grading.name(self.finished)),
+            _ => None,
+        }
+    }
+
+    // milliseconds every finished section of master mode took
+    pub fn section_millis(&self) -> Vec<u64> {
+        self.grading.sections().iter().map(|&ticks| ticks_to_millis(ticks)).collect()
+    }
+
+    // game ended by reaching the goal of the mode
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    pub fn is_game_over(&self) -> bool {
+        self.finished || self.top_out.is_some()
+    }
+
+    // why the game ended
+    pub fn top_out(&self) -> Option<TopOut> {
+        self.top_out
+    }
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new(Config::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::field::Cell;
+
+    // game without countdown and delays, the first piece is already falling
+    fn instant_config() -> Config {
+        Config {
+            ready_ms: 0,
+            go_ms: 0,
+            are_ms: 0,
+            line_clear_ms: 0,
+            ..Config::default()
+        }
+    }
+
+    fn set_piece(engine: &mut Engine, name: &str) {
+        let kind = engine.piece_set.find(name).unwrap();
+        engine.spawn(kind);
+    }
+
+    fn set_next(engine: &mut Engine, name: &str) {
+        engine.next_pieces[0] = engine.piece_set.find(name).unwrap();
+    }
+
+    fn tap(engine: &mut Engine, button: Button) {
+        engine.step(&[Input::Press(button), Input::Release(button)]);
+    }
+
+    // bottom row is full but for the cells the piece lands in
+    fn fill_row_under_ghost(engine: &mut Engine) {
+        let bottom = engine.field.height() as i32 - 1;
+        let landing = engine.ghost_piece().cells();
+        for x in 0..engine.field.width() as i32 {
+            if !landing.contains(&(x, bottom)) {
+                engine.field.set(x, bottom, Cell::Garbage);
+            }
+        }
+    }
+
+    fn locked_cells(engine: &Engine) -> usize {
+        engine.field.rows().iter().flatten().filter(|&&cell| matches!(cell, Cell::Locked(_))).count()
+    }
+
+    #[test]
+    fn hard_drop_locks_piece_on_the_floor() {
+        let mut engine = Engine::new(instant_config());
+        assert_eq!(engine.phase(), Phase::Falling);
+        let ghost = engine.ghost_piece();
+        let distance = ghost.y - engine.piece().y;
+        tap(&mut engine, Button::HardDrop);
+        assert_eq!(engine.pieces(), 1);
+        assert_eq!(locked_cells(&engine), 4);
+        assert!(ghost.cells().iter().all(|&(x, y)| engine.field.is_blocked(x, y)));
+        assert_eq!(engine.score(), 2 * distance);
+        assert!(!engine.is_game_over());
+    }
+
+    #[test]
+    fn filled_line_is_removed() {
+        let mut engine = Engine::new(instant_config());
+        set_piece(&mut engine, "I");
+        fill_row_under_ghost(&mut engine);
+        let distance = engine.ghost_piece().y - engine.piece().y;
+        tap(&mut engine, Button::HardDrop);
+        assert_eq!(engine.lines(), 1);
+        assert_eq!(engine.clear_stats().lines[1], 1);
+        // nothing else was on the board
+        assert!(engine.field.is_empty());
+        assert_eq!(engine.clear_stats().perfect_clears, 1);
+        assert_eq!(engine.score(), 100 + 800 + 2 * distance);
+    }
+
+    #[test]
+    fn spawn_into_blocks_is_block_out() {
+        let mut engine = Engine::new(instant_config());
+        // last hidden rows are full except the first column and the falling piece
+        let top = engine.field.visible_top();
+        let cells = engine.piece().cells();
+        for y in top - 2..top {
+            for x in 1..engine.field.width() as i32 {
+                if !cells.contains(&(x, y)) {
+                    engine.field.set(x, y, Cell::Garbage);
+                }
+            }
+        }
+        tap(&mut engine, Button::HardDrop);
+        assert_eq!(engine.top_out(), Some(TopOut::BlockOut));
+        assert!(engine.is_game_over());
+        assert!(!engine.is_finished());
+    }
+
+    #[test]
+    fn lock_above_the_visible_rows_is_lock_out() {
+        let mut engine = Engine::new(instant_config());
+        let top = engine.field.visible_top();
+        for y in top..engine.field.height() as i32 {
+            for x in 1..engine.field.width() as i32 {
+                engine.field.set(x, y, Cell::Garbage);
+            }
+        }
+        let kind = engine.piece().kind;
+        engine.piece = Piece::spawn(&engine.piece_set, kind, &engine.field);
+        tap(&mut engine, Button::HardDrop);
+        assert_eq!(engine.top_out(), Some(TopOut::LockOut));
+    }
+
+    #[test]
+    fn partial_lock_out_only_when_enabled() {
+        for partial_lock_out in [false, true] {
+            let mut engine = Engine::new(Config { partial_lock_out, ..instant_config() });
+            set_piece(&mut engine, "I");
+            // vertical I at the right wall standing on a stack that reaches the second visible row
+            tap(&mut engine, Button::RotateRight);
+            for _ in 0..engine.field.width() {
+                tap(&mut engine, Button::Right);
+            }
+            let top = engine.field.visible_top();
+            for y in top + 1..engine.field.height() as i32 {
+                for x in 1..engine.field.width() as i32 {
+                    engine.field.set(x, y, Cell::Garbage);
+                }
+            }
+            tap(&mut engine, Button::HardDrop);
+            let expected = if partial_lock_out { Some(TopOut::PartialLockOut) } else { None };
+            assert_eq!(engine.top_out(), expected);
+        }
+    }
+
+    #[test]
+    fn game_over_ignores_further_steps() {
+        let mut engine = Engine::new(instant_config());
+        engine.top_out = Some(TopOut::BlockOut);
+        let ticks = engine.ticks();
+        tap(&mut engine, Button::HardDrop);
+        assert_eq!(engine.pieces(), 0);
+        assert_eq!(engine.ticks(), ticks);
+    }
+
+    #[test]
+    fn restart_starts_a_new_game() {
+        let mut engine = Engine::new(instant_config());
+        tap(&mut engine, Button::HardDrop);
+        engine.restart();
+        assert_eq!((engine.pieces(), engine.lines(), engine.score(), engine.ticks()), (0, 0, 0, 0));
+        assert!(engine.field.is_empty());
+    }
+
+    // piece lays on the floor, lock delay starts counting on the next step
+    fn grounded(config: Config) -> Engine {
+        let mut engine = Engine::new(config);
+        engine.piece.drop_down(&engine.field);
+        engine.lowest_y = engine.piece.bottom();
+        engine
+    }
+
+    fn idle(engine: &mut Engine, ticks: u32) {
+        for _ in 0..ticks {
+            engine.step(&[]);
+        }
+    }
+
+    #[test]
+    fn piece_locks_after_lock_delay() {
+        // 500 ms is 30 ticks
+        let mut engine = grounded(instant_config());
+        idle(&mut engine, 29);
+        assert_eq!(engine.pieces(), 0);
+        idle(&mut engine, 1);
+        assert_eq!(engine.pieces(), 1);
+    }
+
+    #[test]
+    fn move_resets_lock_delay() {
+        let mut engine = grounded(instant_config());
+        idle(&mut engine, 20);
+        tap(&mut engine, Button::Left);
+        idle(&mut engine, 28);
+        assert_eq!(engine.pieces(), 0);
+        idle(&mut engine, 1);
+        assert_eq!(engine.pieces(), 1);
+    }
+
+    #[test]
+    fn piece_locks_when_resets_run_out() {
+        let mut engine = grounded(Config { lock_reset_limit: 2, ..instant_config() });
+        tap(&mut engine, Button::Left);
+        assert_eq!(engine.pieces(), 0);
+        tap(&mut engine, Button::Right);
+        assert_eq!(engine.pieces(), 1);
+    }
+
+    #[test]
+    fn step_reset_ignores_moves() {
+        let mut engine = grounded(Config { lock_reset: LockReset::Step, ..instant_config() });
+        idle(&mut engine, 20);
+        tap(&mut engine, Button::Left);
+        idle(&mut engine, 8);
+        assert_eq!(engine.pieces(), 0);
+        idle(&mut engine, 1);
+        assert_eq!(engine.pieces(), 1);
+    }
+
+    #[test]
+    fn falling_to_a_new_row_resets_lock_delay() {
+        let mut engine = Engine::new(Config { lock_reset: LockReset::Step, ..instant_config() });
+        set_piece(&mut engine, "O");
+        // O rests on a single block under its left column and falls past it when moved right
+        let bottom = engine.field.height() as i32 - 1;
+        let left = engine.piece.cells().iter().map(|&(x, _)| x).min().unwrap();
+        engine.field.set(left, bottom, Cell::Garbage);
+        engine.piece.drop_down(&engine.field);
+        engine.lowest_y = engine.piece.bottom();
+        idle(&mut engine, 20);
+        tap(&mut engine, Button::Right);
+        engine.piece.drop_down(&engine.field);
+        idle(&mut engine, 29);
+        assert_eq!(engine.pieces(), 0);
+        idle(&mut engine, 1);
+        assert_eq!(engine.pieces(), 1);
+    }
+
+    #[test]
+    fn rotating_to_a_new_row_resets_lock_delay() {
+        let mut engine = Engine::new(Config { lock_reset: LockReset::Step, ..instant_config() });
+        set_piece(&mut engine, "T");
+        // T rests on a full row with a hole under its center, rotating in place puts its stem in the hole
+        let bottom = engine.field.height() as i32 - 1;
+        let center = engine.piece.cells().iter().find(|&&(_, y)| y == engine.piece.y).unwrap().0;
+        for x in 0..engine.field.width() as i32 {
+            if x != center {
+                engine.field.set(x, bottom, Cell::Garbage);
+            }
+        }
+        engine.piece.drop_down(&engine.field);
+        engine.lowest_y = engine.piece.bottom();
+        let y = engine.piece.y;
+        idle(&mut engine, 20);
+        tap(&mut engine, Button::RotateRight);
+        assert_eq!(engine.piece.y, y);
+        idle(&mut engine, 28);
+        assert_eq!(engine.pieces(), 0);
+        idle(&mut engine, 1);
+        assert_eq!(engine.pieces(), 1);
+    }
+
+    #[test]
+    fn held_direction_shifts_after_das() {
+        // 167 ms das is 10 ticks, arr 0 moves to the wall
+        let mut engine = Engine::new(Config { arr_ms: 0, ..instant_config() });
+        set_piece(&mut engine, "T");
+        let x = engine.piece.x;
+        engine.step(&[Input::Press(Button::Right)]);
+        assert_eq!(engine.piece.x, x + 1);
+        idle(&mut engine, 8);
+        assert_eq!(engine.piece.x, x + 1);
+        idle(&mut engine, 1);
+        assert!(engine.piece.cells().iter().any(|&(x, _)| x == engine.field.width() as i32 - 1));
+    }
+
+    #[test]
+    fn soft_drop_falls_faster_and_scores_a_point_per_row() {
+        let mut engine = Engine::new(instant_config());
+        let y = engine.piece.y;
+        engine.step(&[Input::Press(Button::SoftDrop)]);
+        // level 1 gravity is one row per second, 20 times that is about 20 rows in a second
+        idle(&mut engine, 59);
+        let rows = engine.piece.y - y;
+        assert!((19..=20).contains(&rows), "{}", rows);
+        assert_eq!(engine.score(), rows);
+    }
+
+    // marathon past the level where gravity reaches 20G
+    fn max_gravity_config() -> Config {
+        Config { start_level: 30, endless: true, ..instant_config() }
+    }
+
+    #[test]
+    fn max_gravity_spawns_on_the_floor_of_tall_boards() {
+        let mut engine = Engine::new(Config { width: 20, height: 40, ..max_gravity_config() });
+        assert_eq!(engine.gravity(), MAX_GRAVITY);
+        assert!(!engine.piece.is_move_down_awailable(&engine.field));
+        tap(&mut engine, Button::HardDrop);
+        assert!(!engine.piece.is_move_down_awailable(&engine.field));
+    }
+
+    #[test]
+    fn max_gravity_drops_into_holes_while_shifting() {
+        let mut engine = Engine::new(Config { arr_ms: 0, ..max_gravity_config() });
+        // bottom row is full but for a hole as wide as the O left of where it spawns
+        let bottom = engine.field.height() as i32 - 1;
+        for x in 0..engine.field.width() as i32 {
+            if x != 2 && x != 3 {
+                engine.field.set(x, bottom, Cell::Garbage);
+            }
+        }
+        set_piece(&mut engine, "O");
+        assert!(engine.piece.cells().contains(&(4, bottom - 1)));
+        // auto shift with arr 0 slides over the hole in one tick, the O must fall in on the way
+        engine.step(&[Input::Press(Button::Left)]);
+        idle(&mut engine, 10);
+        assert_eq!(engine.pieces(), 0);
+        assert!(engine.piece.cells().contains(&(2, bottom)));
+    }
+
+    #[test]
+    fn countdown_before_the_first_piece() {
+        // 800 ms of READY and of GO are 48 ticks each
+        let mut engine = Engine::new(Config::default());
+        assert_eq!(engine.phase(), Phase::Ready);
+        idle(&mut engine, 48);
+        assert_eq!(engine.phase(), Phase::Go);
+        idle(&mut engine, 47);
+        assert_eq!(engine.phase(), Phase::Go);
+        idle(&mut engine, 1);
+        assert_eq!(engine.phase(), Phase::Falling);
+        // the clock starts with the first piece
+        assert_eq!(engine.ticks(), 0);
+        idle(&mut engine, 1);
+        assert_eq!(engine.ticks(), 1);
+    }
+
+    #[test]
+    fn entry_delay_after_lock() {
+        // 100 ms are 6 ticks
+        let mut engine = Engine::new(Config { are_ms: 100, ..instant_config() });
+        tap(&mut engine, Button::HardDrop);
+        assert_eq!(engine.phase(), Phase::Entry);
+        idle(&mut engine, 5);
+        assert_eq!(engine.phase(), Phase::Entry);
+        idle(&mut engine, 1);
+        assert_eq!(engine.phase(), Phase::Falling);
+    }
+
+    #[test]
+    fn filled_lines_stay_during_line_clear_delay() {
+        // 400 ms are 24 ticks, then 100 ms of entry delay
+        let mut engine = Engine::new(Config { are_ms: 100, line_clear_ms: 400, ..instant_config() });
+        set_piece(&mut engine, "I");
+        fill_row_under_ghost(&mut engine);
+        let bottom = engine.field.height() - 1;
+        tap(&mut engine, Button::HardDrop);
+        assert_eq!(engine.phase(), Phase::LineClear);
+        assert_eq!(engine.clearing_lines(), vec![bottom]);
+        idle(&mut engine, 23);
+        assert_eq!(engine.clearing_lines(), vec![bottom]);
+        idle(&mut engine, 1);
+        assert_eq!(engine.phase(), Phase::Entry);
+        assert!(engine.clearing_lines().is_empty());
+        assert!(engine.field.is_empty());
+        idle(&mut engine, 6);
+        assert_eq!(engine.phase(), Phase::Falling);
+    }
+
+    #[test]
+    fn inputs_during_delays_do_not_move_the_next_piece() {
+        let mut engine = Engine::new(Config { are_ms: 100, ..instant_config() });
+        tap(&mut engine, Button::HardDrop);
+        tap(&mut engine, Button::Left);
+        tap(&mut engine, Button::HardDrop);
+        idle(&mut engine, 4);
+        assert_eq!(engine.phase(), Phase::Falling);
+        assert_eq!(engine.pieces(), 1);
+        let spawned = Piece::spawn(&engine.piece_set, engine.piece.kind, &engine.field);
+        assert_eq!(engine.piece.x, spawned.x);
+    }
+
+    #[test]
+    fn rotation_held_at_spawn_rotates_the_piece() {
+        let mut engine = Engine::new(Config { are_ms: 100, ..instant_config() });
+        set_next(&mut engine, "T");
+        tap(&mut engine, Button::HardDrop);
+        engine.step(&[Input::Press(Button::RotateLeft)]);
+        idle(&mut engine, 5);
+        assert_eq!(engine.phase(), Phase::Falling);
+        assert_eq!(engine.piece.rotation, 3);
+    }
+
+    #[test]
+    fn rotation_held_from_the_previous_piece_rotates_the_next() {
+        let mut engine = Engine::new(Config { are_ms: 100, ..instant_config() });
+        set_piece(&mut engine, "T");
+        set_next(&mut engine, "T");
+        engine.step(&[Input::Press(Button::RotateRight)]);
+        assert_eq!(engine.piece.rotation, 1);
+        tap(&mut engine, Button::HardDrop);
+        idle(&mut engine, 6);
+        assert_eq!(engine.phase(), Phase::Falling);
+        assert_eq!(engine.piece.rotation, 1);
+        // released before the spawn, nothing happens
+        engine.step(&[Input::Release(Button::RotateRight)]);
+        set_next(&mut engine, "T");
+        tap(&mut engine, Button::HardDrop);
+        idle(&mut engine, 6);
+        assert_eq!(engine.piece.rotation, 0);
+    }
+
+    #[test]
+    fn hold_held_at_spawn_holds_the_piece() {
+        let mut engine = Engine::new(Config { are_ms: 100, ..instant_config() });
+        tap(&mut engine, Button::HardDrop);
+        let next = engine.next_pieces()[0];
+        let after_next = engine.next_pieces()[1];
+        engine.step(&[Input::Press(Button::Hold)]);
+        idle(&mut engine, 5);
+        assert_eq!(engine.phase(), Phase::Falling);
+        assert_eq!(engine.hold_piece(), Some(next));
+        assert_eq!(engine.piece.kind, after_next);
+        assert!(!engine.can_hold());
+    }
+
+    #[test]
+    fn hold_pressed_while_falling_holds_once() {
+        let mut engine = Engine::new(instant_config());
+        let first = engine.piece.kind;
+        let next = engine.next_pieces()[0];
+        engine.step(&[Input::Press(Button::Hold)]);
+        assert_eq!(engine.hold_piece(), Some(first));
+        assert_eq!(engine.piece.kind, next);
+        // still held when the next piece appears, that one goes to hold and the first comes back
+        let after_next = engine.next_pieces()[0];
+        tap(&mut engine, Button::HardDrop);
+        assert_eq!(engine.hold_piece(), Some(after_next));
+        assert_eq!(engine.piece.kind, first);
+        assert!(!engine.can_hold());
+    }
+
+    #[test]
+    fn sprint_takes_a_split_every_ten_lines_and_finishes_at_forty() {
+        let mut engine = Engine::new(Config { mode: Mode::Sprint, ..instant_config() });
+        engine.lines = 9;
+        engine.ticks = 600;
+        set_piece(&mut engine, "I");
+        fill_row_under_ghost(&mut engine);
+        tap(&mut engine, Button::HardDrop);
+        assert_eq!(engine.split_millis(), vec![10_000]);
+        engine.lines = 39;
+        fill_row_under_ghost(&mut engine);
+        tap(&mut engine, Button::HardDrop);
+        assert_eq!(engine.split_millis().len(), 4);
+        assert!(engine.is_finished());
+    }
+
+    #[test]
+    fn master_ignores_move_resets() {
+        // master lock delay is 30 ticks in the first sections whatever the config says
+        let mut engine = grounded(Config { mode: Mode::Master, lock_reset: LockReset::Move, lock_delay_ms: 2000, ..instant_config() });
+        assert_eq!(engine.level(), 0);
+        idle(&mut engine, 20);
+        tap(&mut engine, Button::Left);
+        idle(&mut engine, 8);
+        assert_eq!(engine.pieces(), 0);
+        idle(&mut engine, 1);
+        assert_eq!(engine.pieces(), 1);
+        assert_eq!(engine.level(), 1);
+    }
+
+    #[test]
+    fn master_grade_is_shown_only_in_master() {
+        assert_eq!(Engine::new(instant_config()).grade(), None);
+        assert_eq!(Engine::new(Config { mode: Mode::Master, ..instant_config() }).grade(), Some("9"));
+    }
+
+    #[test]
+    fn master_clears_are_multiplied_by_section() {
+        let mut engine = Engine::new(Config { mode: Mode::Master, ..instant_config() });
+        while engine.level() < 900 {
+            engine.leveling.line_clear(LineClear::plain(4), false);
+        }
+        assert_eq!(engine.level(), 900);
+        // 20G, the I is already on the floor
+        set_piece(&mut engine, "I");
+        fill_row_under_ghost(&mut engine);
+        tap(&mut engine, Button::HardDrop);
+        // single and perfect clear in the tenth section
+        assert_eq!(engine.score(), (100 + 800) * 10);
+    }
+}
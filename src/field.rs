@@ -0,0 +1,122 @@
+use crate::piece::PieceKind;
+
+pub const MIN_WIDTH: usize = 4;
+pub const MAX_WIDTH: usize = 20;
+pub const MIN_HEIGHT: usize = 4;
+pub const MAX_HEIGHT: usize = 40;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Cell {
+    Empty,
+    // block of a locked piece, remembers which piece it came from
+    Locked(PieceKind),
+    // block of a row that came from below
+    Garbage,
+}
+
+// matrix of laying pieces, visible rows at the bottom and the same count of hidden buffer rows above them
+#[derive(Clone, Debug)]
+pub struct GameField {
+    width: usize,
+    visible_height: usize,
+    rows: Vec<Vec<Cell>>,
+}
+
+impl GameField {
+    // size is clamped to MIN_WIDTH..=MAX_WIDTH and MIN_HEIGHT..=MAX_HEIGHT
+    pub fn new(width: usize, visible_height: usize) -> GameField {
+        let width = width.clamp(MIN_WIDTH, MAX_WIDTH);
+        let visible_height = visible_height.clamp(MIN_HEIGHT, MAX_HEIGHT);
+        GameField {
+            width,
+            visible_height,
+            rows: vec![vec![Cell::Empty; width]; 2 * visible_height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    // all rows including hidden ones
+    pub fn height(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn visible_height(&self) -> usize {
+        self.visible_height
+    }
+
+    // first visible row
+    pub fn visible_top(&self) -> i32 {
+        (self.height() - self.visible_height) as i32
+    }
+
+    pub fn rows(&self) -> &[Vec<Cell>] {
+        &self.rows
+    }
+
+    // true if cell is outside of the matrix or taken by a laying piece
+    pub fn is_blocked(&self, x: i32, y: i32) -> bool {
+        if !(0..self.width as i32).contains(&x) || !(0..self.height() as i32).contains(&y) {
+            return true;
+        }
+        self.rows[y as usize][x as usize] != Cell::Empty
+    }
+
+    // cells outside of the matrix are ignored
+    pub fn set(&mut self, x: i32, y: i32, cell: Cell) {
+        if !(0..self.width as i32).contains(&x) || !(0..self.height() as i32).contains(&y) {
+            return;
+        }
+        self.rows[y as usize][x as usize] = cell;
+    }
+
+    // rows taken completely by laying pieces, top to bottom
+    pub fn filled_lines(&self) -> Vec<usize> {
+        (0..self.rows.len()).filter(|&y| self.rows[y].iter().all(|&c| c != Cell::Empty)).collect()
+    }
+
+    // true if nothing is left after filled lines are removed
+    pub fn is_empty_without_filled_lines(&self) -> bool {
+        self.rows.iter().all(|row| row.iter().all(|&c| c != Cell::Empty) || row.iter().all(|&c| c == Cell::Empty))
+    }
+
+    // remove filled lines, move everything above down, return count of removed lines
+    pub fn remove_filled_lines(&mut self) -> i32 {
+        let mut filled_lines = 0;
+        for y in 0..self.rows.len() {
+            if self.rows[y].iter().all(|&c| c != Cell::Empty) {
+                self.rows[..=y].rotate_right(1);
+                self.rows[0] = vec![Cell::Empty; self.width];
+                filled_lines += 1;
+            }
+        }
+        filled_lines
+    }
+
+    // move everything one row up and put `row` at the bottom, the top row is lost
+    pub fn push_row(&mut self, row: Vec<Cell>) {
+        self.rows.rotate_left(1);
+        *self.rows.last_mut().unwrap() = row;
+    }
+
+    // garbage row with the only hole at `hole`
+    pub fn garbage_row(&self, hole: usize) -> Vec<Cell> {
+        (0..self.width).map(|x| if x == hole { Cell::Empty } else { Cell::Garbage }).collect()
+    }
+
+    pub fn garbage_rows(&self) -> usize {
+        self.rows.iter().filter(|row| row.contains(&Cell::Garbage)).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.iter().all(|row| row.iter().all(|&c| c == Cell::Empty))
+    }
+}
+
+impl Default for GameField {
+    fn default() -> Self {
+        GameField::new(10, 20)
+    }
+}
@@ -0,0 +1,172 @@
+// buttons of a game controller
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Button {
+    Left,
+    Right,
+    RotateLeft,
+    RotateRight,
+    SoftDrop,
+    HardDrop,
+    Hold,
+}
+
+impl Button {
+    pub const COUNT: usize = 7;
+}
+
+// what happened with a button since the previous step
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Input {
+    Press(Button),
+    Release(Button),
+}
+
+// auto shift moves to the wall at once
+pub const SHIFT_TO_WALL: u32 = u32::MAX;
+
+// held buttons and delayed auto shift state
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Controller {
+    held: [bool; Button::COUNT],
+    // -1 left, 1 right, 0 no shift; the last pressed of held directions wins
+    shift_direction: i32,
+    das_counter: u32,
+    arr_counter: u32,
+}
+
+impl Controller {
+    pub fn press(&mut self, button: Button) {
+        self.held[button as usize] = true;
+        match button {
+            Button::Left => self.start_shift(-1),
+            Button::Right => self.start_shift(1),
+            _ => (),
+        }
+    }
+
+    pub fn release(&mut self, button: Button) {
+        self.held[button as usize] = false;
+        match button {
+            Button::Left if self.shift_direction == -1 => {
+                self.start_shift(if self.is_held(Button::Right) { 1 } else { 0 });
+            },
+            Button::Right if self.shift_direction == 1 => {
+                self.start_shift(if self.is_held(Button::Left) { -1 } else { 0 });
+            },
+            _ => (),
+        }
+    }
+
+    pub fn is_held(&self, button: Button) -> bool {
+        self.held[button as usize]
+    }
+
+    pub fn shift_direction(&self) -> i32 {
+        self.shift_direction
+    }
+
+    fn start_shift(&mut self, direction: i32) {
+        self.shift_direction = direction;
+        self.das_counter = 0;
+        self.arr_counter = 0;
+    }
+
+    // advance one tick, return how many cells piece should auto shift in `shift_direction`,
+    // SHIFT_TO_WALL when auto repeat rate is 0
+    pub fn auto_shift(&mut self, das_ticks: u32, arr_ticks: u32) -> u32 {
+        if self.shift_direction == 0 {
+            return 0;
+        }
+        if self.das_counter < das_ticks {
+            self.das_counter += 1;
+            if self.das_counter < das_ticks {
+                return 0;
+            }
+        } else if arr_ticks > 0 {
+            self.arr_counter += 1;
+            if self.arr_counter < arr_ticks {
+                return 0;
+            }
+            self.arr_counter = 0;
+        }
+        if arr_ticks == 0 {
+            return SHIFT_TO_WALL;
+        }
+        1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shifts(controller: &mut Controller, das_ticks: u32, arr_ticks: u32, ticks: usize) -> Vec<u32> {
+        (0..ticks).map(|_| controller.auto_shift(das_ticks, arr_ticks)).collect()
+    }
+
+    #[test]
+    fn nothing_shifts_without_direction() {
+        let mut controller = Controller::default();
+        assert_eq!(shifts(&mut controller, 10, 2, 20), vec![0; 20]);
+    }
+
+    #[test]
+    fn first_auto_shift_after_das_then_every_arr() {
+        let mut controller = Controller::default();
+        controller.press(Button::Right);
+        let moves = shifts(&mut controller, 10, 2, 16);
+        assert_eq!(moves, vec![0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 1, 0, 1, 0, 1]);
+        assert_eq!(controller.shift_direction(), 1);
+    }
+
+    #[test]
+    fn arr_of_one_tick_shifts_every_tick() {
+        let mut controller = Controller::default();
+        controller.press(Button::Left);
+        assert_eq!(shifts(&mut controller, 3, 1, 6), vec![0, 0, 1, 1, 1, 1]);
+    }
+
+    #[test]
+    fn zero_arr_shifts_to_the_wall() {
+        let mut controller = Controller::default();
+        controller.press(Button::Left);
+        assert_eq!(shifts(&mut controller, 3, 0, 4), vec![0, 0, SHIFT_TO_WALL, SHIFT_TO_WALL]);
+    }
+
+    #[test]
+    fn last_pressed_direction_wins_and_restarts_das() {
+        let mut controller = Controller::default();
+        controller.press(Button::Left);
+        shifts(&mut controller, 3, 1, 5);
+        controller.press(Button::Right);
+        assert_eq!(controller.shift_direction(), 1);
+        assert_eq!(shifts(&mut controller, 3, 1, 3), vec![0, 0, 1]);
+        // back to the still held left, charging again
+        controller.release(Button::Right);
+        assert_eq!(controller.shift_direction(), -1);
+        assert_eq!(shifts(&mut controller, 3, 1, 3), vec![0, 0, 1]);
+        controller.release(Button::Left);
+        assert_eq!(controller.shift_direction(), 0);
+    }
+
+    #[test]
+    fn releasing_the_other_direction_keeps_the_charge() {
+        let mut controller = Controller::default();
+        controller.press(Button::Left);
+        controller.press(Button::Right);
+        shifts(&mut controller, 3, 1, 5);
+        controller.release(Button::Left);
+        assert_eq!(controller.shift_direction(), 1);
+        assert_eq!(controller.auto_shift(3, 1), 1);
+    }
+
+    #[test]
+    fn held_buttons_are_tracked() {
+        let mut controller = Controller::default();
+        controller.press(Button::SoftDrop);
+        assert!(controller.is_held(Button::SoftDrop));
+        assert!(!controller.is_held(Button::HardDrop));
+        controller.release(Button::SoftDrop);
+        assert!(!controller.is_held(Button::SoftDrop));
+    }
+}
@@ -0,0 +1,212 @@
+use crate::master::SECTION_LEVELS;
+use crate::scoring::{LineClear, TSpin};
+
+// when the level goes up
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LevelProgression {
+    // one level per 10 cleared lines, classic rules
+    EveryTenLines,
+    // guideline fixed goal, this many cleared lines per level
+    FixedGoal(i32),
+    // guideline variable goal, 5 * level awarded lines per level,
+    // tetrises and T-spins award more than they clear
+    VariableGoal,
+    // master mode, one level per piece and one per cleared line,
+    // the last level of a section is only left by clearing lines
+    Sections,
+}
+
+impl LevelProgression {
+    // progressions selectable in menu, sections belong to master mode
+    pub const ALL: [LevelProgression; 3] = [
+        LevelProgression::EveryTenLines,
+        LevelProgression::FixedGoal(5),
+        LevelProgression::VariableGoal,
+    ];
+
+    pub fn name(self) -> String {
+        match self {
+            LevelProgression::EveryTenLines => "every 10 lines".to_string(),
+            LevelProgression::FixedGoal(lines) => format!("fixed goal {}", lines),
+            LevelProgression::VariableGoal => "variable goal".to_string(),
+            LevelProgression::Sections => "sections".to_string(),
+        }
+    }
+
+    // the progression after this one, wraps around
+    pub fn cycle(self) -> LevelProgression {
+        let i = LevelProgression::ALL.iter().position(|&p| p == self).unwrap_or(0);
+        LevelProgression::ALL[(i + 1) % LevelProgression::ALL.len()]
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Leveling {
+    progression: LevelProgression,
+    level: i32,
+    // level stops growing here
+    max_level: Option<i32>,
+    // lines counted towards the current level goal
+    progress: i32,
+}
+
+impl Leveling {
+    pub fn new(progression: LevelProgression, start_level: i32, max_level: Option<i32>) -> Leveling {
+        Leveling {
+            progression,
+            level: match progression {
+                // sections always count from level 0
+                LevelProgression::Sections => 0,
+                _ => start_level.max(1),
+            },
+            max_level,
+            progress: 0,
+        }
+    }
+
+    // count lines of a clear, `back_to_back` if it got back to back bonus
+    pub fn line_clear(&mut self, clear: LineClear, back_to_back: bool) {
+        if self.progression == LevelProgression::Sections {
+            self.section_clear(clear.lines);
+            return;
+        }
+        self.progress += match self.progression {
+            LevelProgression::VariableGoal => {
+                let awarded = match (clear.t_spin, clear.lines) {
+                    (TSpin::None, 0) => 0,
+                    (TSpin::None, 1) => 1,
+                    (TSpin::None, 2) => 3,
+                    (TSpin::None, 3) => 5,
+                    (TSpin::None, _) => 8,
+                    (TSpin::Mini, 0) => 1,
+                    (TSpin::Mini, _) => 2,
+                    (TSpin::Full, lines) => 4 * (lines + 1),
+                };
+                if back_to_back { awarded * 3 / 2 } else { awarded }
+            },
+            _ => clear.lines,
+        };
+        while self.progress >= self.goal() && self.max_level.is_none_or(|max| self.level < max) {
+            self.progress -= self.goal();
+            self.level += 1;
+        }
+    }
+
+    // placed piece in sections progression, every piece counts as one level
+    fn section_clear(&mut self, lines: i32) {
+        let max = self.max_level.unwrap_or(i32::MAX);
+        // piece alone never finishes a section or the game
+        let stop = (self.level / SECTION_LEVELS * SECTION_LEVELS + SECTION_LEVELS - 1).min(max - 1);
+        if self.level < stop {
+            self.level += 1;
+        }
+        self.level = (self.level + lines).min(max);
+    }
+
+    // lines needed to finish the current level, levels to the next section in sections progression
+    pub fn goal(&self) -> i32 {
+        match self.progression {
+            LevelProgression::Sections => SECTION_LEVELS,
+            LevelProgression::EveryTenLines => 10,
+            LevelProgression::FixedGoal(lines) => lines.max(1),
+            LevelProgression::VariableGoal => 5 * self.level,
+        }
+    }
+
+    pub fn lines_to_next_level(&self) -> i32 {
+        match self.progression {
+            LevelProgression::Sections => SECTION_LEVELS - self.level % SECTION_LEVELS,
+            _ => self.goal() - self.progress,
+        }
+    }
+
+    pub fn level(&self) -> i32 {
+        self.level
+    }
+}
+
+// rows per tick the piece falls at `level`, guideline curve up to 20G
+pub fn guideline_gravity(level: i32) -> f64 {
+    let base = 0.8 - (level - 1) as f64 * 0.007;
+    if base <= 0. {
+        return MAX_GRAVITY;
+    }
+    let seconds_per_row = base.powi(level - 1);
+    (1. / (seconds_per_row * 60.)).min(MAX_GRAVITY)
+}
+
+// 20G, piece is on the floor as soon as it spawns or moves, on any board height
+pub const MAX_GRAVITY: f64 = 20.;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_ten_lines() {
+        let mut leveling = Leveling::new(LevelProgression::EveryTenLines, 1, None);
+        for _ in 0..9 {
+            leveling.line_clear(LineClear::plain(1), false);
+        }
+        assert_eq!((leveling.level(), leveling.lines_to_next_level()), (1, 1));
+        leveling.line_clear(LineClear::plain(4), false);
+        assert_eq!((leveling.level(), leveling.lines_to_next_level()), (2, 7));
+    }
+
+    #[test]
+    fn fixed_goal() {
+        let mut leveling = Leveling::new(LevelProgression::FixedGoal(5), 3, None);
+        leveling.line_clear(LineClear::plain(4), false);
+        leveling.line_clear(LineClear::plain(4), false);
+        assert_eq!((leveling.level(), leveling.lines_to_next_level()), (4, 2));
+    }
+
+    #[test]
+    fn variable_goal_awards_more_for_difficult_clears() {
+        // level 1 needs 5 awarded lines, a tetris awards 8
+        let mut leveling = Leveling::new(LevelProgression::VariableGoal, 1, None);
+        leveling.line_clear(LineClear::plain(4), false);
+        assert_eq!((leveling.level(), leveling.lines_to_next_level()), (2, 7));
+        // T-spin double awards 12, back to back 18
+        leveling.line_clear(LineClear { lines: 2, t_spin: TSpin::Full, perfect_clear: false }, true);
+        assert_eq!((leveling.level(), leveling.lines_to_next_level()), (3, 4));
+        leveling.line_clear(LineClear { lines: 0, t_spin: TSpin::Mini, perfect_clear: false }, false);
+        assert_eq!(leveling.lines_to_next_level(), 3);
+    }
+
+    #[test]
+    fn level_stops_at_max() {
+        let mut leveling = Leveling::new(LevelProgression::EveryTenLines, 14, Some(15));
+        for _ in 0..10 {
+            leveling.line_clear(LineClear::plain(4), false);
+        }
+        assert_eq!(leveling.level(), 15);
+    }
+
+    #[test]
+    fn start_level_is_at_least_one() {
+        assert_eq!(Leveling::new(LevelProgression::EveryTenLines, 0, None).level(), 1);
+    }
+
+    #[test]
+    fn guideline_gravity_curve() {
+        // level 1 is a row per second
+        assert!((guideline_gravity(1) - 1. / 60.).abs() < 1e-9);
+        // level 15 is about 2.36G
+        assert!((guideline_gravity(15) - 2.36).abs() < 0.01);
+        for level in 1..30 {
+            assert!(guideline_gravity(level + 1) >= guideline_gravity(level));
+        }
+        assert_eq!(guideline_gravity(20), MAX_GRAVITY);
+        assert_eq!(guideline_gravity(200), MAX_GRAVITY);
+    }
+
+    #[test]
+    fn cycle_visits_every_progression() {
+        let mut progression = LevelProgression::EveryTenLines;
+        for _ in 0..LevelProgression::ALL.len() {
+            progression = progression.cycle();
+        }
+        assert_eq!(progression, LevelProgression::EveryTenLines);
+    }
+}
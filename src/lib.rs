@@ -0,0 +1,25 @@
+pub mod config;
+pub mod engine;
+pub mod field;
+pub mod input;
+pub mod level;
+pub mod master;
+pub mod mode;
+pub mod piece;
+pub mod piece_set;
+pub mod randomizer;
+pub mod records;
+pub mod scoring;
+pub mod srs;
+
+pub use config::Config;
+pub use engine::{Engine, Phase, TopOut};
+pub use field::{Cell, GameField};
+pub use input::{Button, Input};
+pub use level::LevelProgression;
+pub use mode::Mode;
+pub use piece::{Piece, PieceKind};
+pub use piece_set::PieceSet;
+pub use randomizer::{Randomizer, RandomizerKind};
+pub use records::Records;
+pub use scoring::{ClearStats, LineClear, TSpin};
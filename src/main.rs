@@ -1,27 +1,56 @@
 extern crate sdl2;
 
 use std::path::Path;
-use std::time::Duration;
-use sdl2::render::{TextureCreator, Texture, Canvas};
+use std::time::{Duration, Instant};
+use sdl2::render::{BlendMode, TextureCreator, Texture, Canvas};
 use sdl2::pixels::Color;
-use sdl2::event::Event;
+use sdl2::event::{Event, WindowEvent};
 use sdl2::keyboard::Keycode;
 use sdl2::rect::Rect;
 use sdl2::image::LoadTexture;
 use sdl2::video::{Window, WindowContext};
 use sdl2::ttf::Font;
-use rand;
-
-type GameField = [[char; 10]; 20];
+use tetris::{Button, Cell, Config, Engine, GameField, Input, Mode, Phase, Piece, PieceKind, PieceSet, Records};
+use tetris::engine::TICK;
+use tetris::config::MAX_NEXT_COUNT;
+use tetris::master::MASTER_MAX_LEVEL;
+use tetris::mode::{DIG_ROWS, MAX_START_LEVEL};
+use tetris::records::sprint_rules;
 
 enum GameState {
     Menu,
     Play,
+    Pause,
     Death,
+    Finish,
+}
+
+// personal bests are kept next to the assets directory
+const RECORDS_PATH: &str = "./records.ron";
+
+// seconds as hours:minutes:seconds
+fn format_time(seconds: f64) -> String {
+    let seconds = seconds as u64;
+    format!("{}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)
+}
+
+// milliseconds as minutes:seconds.milliseconds
+fn format_millis(ms: u64) -> String {
+    format!("{}:{:02}.{:03}", ms / 60000, ms / 1000 % 60, ms % 1000)
+}
+
+// split time with difference to the personal best split, colored by whether it is ahead
+fn format_split(lines: i32, ms: u64, best: Option<u64>, colors: (Color, Color, Color)) -> (String, Color) {
+    let (fg_color, hl_color, at_color) = colors;
+    match best {
+        Some(best) if ms <= best => (format!("{}: {} -{:.2}", lines, format_millis(ms), (best - ms) as f64 / 1000.), hl_color),
+        Some(best) => (format!("{}: {} +{:.2}", lines, format_millis(ms), (ms - best) as f64 / 1000.), at_color),
+        None => (format!("{}: {}", lines, format_millis(ms)), fg_color),
+    }
 }
 
 fn load_texture<'a>(tc: &'a TextureCreator<WindowContext>, filename: &str) -> Texture<'a> {
-    return tc.load_texture(format!("./assets/{}", filename)).unwrap();
+    tc.load_texture(format!("./assets/{}", filename)).unwrap()
 }
 
 fn set_text(canvas: &mut Canvas<Window>, font: &Font, tc: &TextureCreator<WindowContext>, color: Color, text: &str, position_rect: Rect) {
@@ -32,425 +61,108 @@ fn set_text(canvas: &mut Canvas<Window>, font: &Font, tc: &TextureCreator<Window
 
 fn write_tetris_by_textures(canvas: &mut Canvas<Window>, texture: &Texture) {
     for x in 0..19 {
-        if !vec![3, 6, 10, 13, 15, 16].iter().any(|&i| i == x) {
+        if ![3, 6, 10, 13, 15, 16].contains(&x) {
             canvas.copy(texture, None, Some(Rect::new(20+x*20, 20, 20, 20))).unwrap();
         }
-        if !vec![0, 2, 3, 5, 6, 7, 9, 10, 12, 13, 15, 16, 18].iter().any(|&i| i == x) {
+        if ![0, 2, 3, 5, 6, 7, 9, 10, 12, 13, 15, 16, 18].contains(&x) {
             canvas.copy(texture, None, Some(Rect::new(20+x*20, 40, 20, 20))).unwrap();
         }
-        if !vec![0, 2, 3, 6, 7, 9, 10, 12, 13, 15, 18].iter().any(|&i| i == x) {
+        if ![0, 2, 3, 6, 7, 9, 10, 12, 13, 15, 18].contains(&x) {
             canvas.copy(texture, None, Some(Rect::new(20+x*20, 60, 20, 20))).unwrap();
         }
     }
 }
 
-struct Piece {
-    x: i32,
-    y: i32,
-    rotation: i32,
-    literal: char,
-}
-
-impl Piece {
-    fn new() -> Piece {
-        let lit = match rand::random::<u8>() % 6 {
-            0 => 'Y',
-            1 => 'C',
-            2 => 'P',
-            3 => 'D',
-            4 => 'R',
-            5 => 'O',
-            6 => 'G',
-            _ => 'N',
-        };
-        return Piece {
-            x: 5,
-            y: match lit {
-                'C' => 0,
-                _ => 1,
-            },
-            rotation: 0,
-            literal: lit,
-        }
+fn button_for_key(keycode: Keycode) -> Option<Button> {
+    match keycode {
+        Keycode::Z => Some(Button::RotateLeft),
+        Keycode::X => Some(Button::RotateRight),
+        Keycode::Left => Some(Button::Left),
+        Keycode::Right => Some(Button::Right),
+        Keycode::Down => Some(Button::SoftDrop),
+        Keycode::Up => Some(Button::HardDrop),
+        Keycode::C => Some(Button::Hold),
+        _ => None,
     }
+}
 
-    // return true if moved, false if something blocks move
-    fn move_x(&mut self, delta: i32, field: &GameField) -> bool {
-        let body = self.body();
-        match delta {
-            d if d < 0 => {
-                for y in 0..4 {
-                    for x in 0..4 {
-                        if body[y][x] != ' ' {
-                            let left_cell_i = self.x + x as i32 - 2 - 1;
-                            let cy = self.y - 2 + y as i32;
-
-                            if left_cell_i < 0 {
-                                return false;
-                            }
-                            if cy < 0 {
-                                continue;
-                            }
-                            if field[cy as usize][left_cell_i as usize] == 'N' {
-                                return false;
-                            }
-                            break;
-                        }
-                    }
-                }
-            },
-            d if d > 0 => {
-                for y in 0..4 {
-                    for x in (0..4).rev() {
-                        if body[y][x] != ' ' {
-                            let right_cell_i = self.x + x as i32 - 2 + 1;
-                            let cy = self.y - 2 + y as i32;
-
-                            if right_cell_i >= 10 {
-                                return false;
-                            }
-                            if cy < 0 {
-                                continue;
-                            }
-                            if field[cy as usize][right_cell_i as usize] == 'N' {
-                                return false;
-                            }
-                            break;
-                        }
-                    }
-                }
-            },
-            _ => (),
-        }
-        self.x += delta;
-        return true
-    }
-
-    fn is_move_down_awailable(&self, field: &GameField) -> bool {
-        let body = self.body();
-        for x in 0..4 {
-            for y in (0..4).rev() {
-                if body[y][x] != ' ' {
-                    let below_cell_i = self.y + y as i32 - 2 + 1;
-                    let cx = self.x - 2 + x as i32;
-                    if cx < 0 || below_cell_i < 0 {
-                        continue;
-                    }
-                    if below_cell_i >= 20 || field[below_cell_i as usize][cx as usize] == 'N' {
-                        return false;
-                    }
-                    break;
-                }
-            }
-        }
-        return true;
-    }
-
-    fn force_move_y(&mut self, delta: i32) {
-        self.y += delta;
-    }
-
-    fn drop_down(&mut self, field: &GameField) {
-        while self.is_move_down_awailable(&field) {
-            self.force_move_y(1);
-        }
-    }
-
-    fn rotate(&mut self, field: &GameField, direction: i32) {
-        let rot_backup = self.rotation;
-        self.rotation = (self.rotation + direction) % 4;
-        if self.rotation < 0 {
-            self.rotation = 3;
-        }
-        let body = self.body();
-        self.rotation = rot_backup;
-
-        for y in 0..4 {
-            let mut x = -1;
-            while x < 3 {
-                x += 1;
-
-                let fx = self.x + x - 2;
-                let fy = self.y + y - 2;
-
-                if body[y as usize][x as usize] == ' ' {
-                    continue;
-                }
-
-                if fx < 0 {
-                    x -= 1;
-                    self.x += 1;
-                    continue;
-                }
-                if fx >= 10 {
-                    x -= 1;
-                    self.x -= 1;
-                    continue;
-                }
-
-                if fy < 0 {
-                    continue
-                }
+// cell size of a preview, pieces with boxes larger than 4x4 are scaled down to fit the space of a 4x4 one
+fn preview_cell_size(piece: &Piece, cell_size: u32) -> u32 {
+    cell_size * 4 / (piece.def.size.0.max(piece.def.size.1).max(4) as u32)
+}
 
-                if fy >= 20 || field[fy as usize][fx as usize] == 'N' {
-                    return;
-                }
-            }
-        }
+// top and bottom row of the spawn rotation in the box
+fn spawn_rows(piece: &Piece) -> (i32, i32) {
+    let rows = piece.def.rotations[0].iter().map(|&(_, y)| y);
+    (rows.clone().min().unwrap(), rows.max().unwrap())
+}
 
-        self.rotation = (self.rotation + direction) % 4;
-        if self.rotation < 0 {
-            self.rotation = 3;
-        }
+// draw piece in spawn rotation with its top row at `top` and its box at `left`
+fn draw_piece_preview<'a>(canvas: &mut Canvas<Window>, get_texture: &dyn Fn(PieceKind) -> &'a Texture<'a>, set: &PieceSet, kind: PieceKind, left: i32, top: i32, cell_size: u32) {
+    let piece = Piece::new(set, kind);
+    let cell_size = preview_cell_size(&piece, cell_size);
+    let top = top - spawn_rows(&piece).0 * cell_size as i32;
+    for (x, y) in piece.cells() {
+        canvas.copy(get_texture(kind), None, Some(Rect::new(left + x * cell_size as i32, top + y * cell_size as i32, cell_size, cell_size))).unwrap();
     }
+}
 
-    fn put_on_a_field(&self, field: &mut GameField, force_gray: bool) {
-        let body = self.body();
-
-        for y in 0..field.len() {
-            for x in 0..field[y].len() {
-                if field[y][x] != 'N' {
-                    field[y][x] = ' '
-                }
-            }
-        }
-
-        for y in 0..4 {
-            for x in 0..4 {
-                let fx = self.x + x - 2;
-                let fy = self.y + y - 2;
-
-                if fx < 0 || fx >= 10 || fy < 0 || fy >= 20 || body[y as usize][x as usize] == ' ' {
-                    continue;
-                }
+// height of the tallest preview of the set, what a slot of the next queue needs
+fn preview_slot_height(set: &PieceSet, cell_size: u32) -> i32 {
+    set.kinds().iter().map(|&kind| {
+        let piece = Piece::new(set, kind);
+        let (top, bottom) = spawn_rows(&piece);
+        (bottom - top + 1) * preview_cell_size(&piece, cell_size) as i32
+    }).max().unwrap_or(0)
+}
 
-                field[fy as usize][fx as usize] = match force_gray {
-                    true => 'N',
-                    false => body[y as usize][x as usize],
-                }
-            }
-        }
-    }
+// board sizes selectable in menu
+const BOARD_SIZES: [(usize, usize); 6] = [(10, 20), (4, 20), (6, 20), (8, 20), (12, 24), (20, 40)];
 
-    fn body(&self) -> [[char; 4]; 4] {
-        let lit = self.literal;
-        let rot = self.rotation;
-
-        let default = [
-            [' ', ' ', ' ', ' '],
-            [' ', lit, lit, ' '],
-            [' ', lit, lit, ' '],
-            [' ', ' ', ' ', ' '],
-        ];
-
-        return match lit {
-            'Y' => [
-                [' ', ' ', ' ', ' '],
-                [' ', lit, lit, ' '],
-                [' ', lit, lit, ' '],
-                [' ', ' ', ' ', ' '],
-            ],
-            'C' => match rot {
-                0 => [
-                    [' ', ' ', ' ', ' '],
-                    [lit, lit, lit, lit],
-                    [' ', ' ', ' ', ' '],
-                    [' ', ' ', ' ', ' '],
-                ],
-                1 => [
-                    [' ', ' ', lit, ' '],
-                    [' ', ' ', lit, ' '],
-                    [' ', ' ', lit, ' '],
-                    [' ', ' ', lit, ' '],
-                ],
-                2 => [
-                    [' ', ' ', ' ', ' '],
-                    [' ', ' ', ' ', ' '],
-                    [lit, lit, lit, lit],
-                    [' ', ' ', ' ', ' '],
-                ],
-                3 => [
-                    [' ', lit, ' ', ' '],
-                    [' ', lit, ' ', ' '],
-                    [' ', lit, ' ', ' '],
-                    [' ', lit, ' ', ' '],
-                ],
-                _ => default,
-            },
-            'P' => match rot {
-                0 => [
-                    [' ', lit, ' ', ' '],
-                    [lit, lit, lit, ' '],
-                    [' ', ' ', ' ', ' '],
-                    [' ', ' ', ' ', ' '],
-                ],
-                1 => [
-                    [' ', lit, ' ', ' '],
-                    [' ', lit, lit, ' '],
-                    [' ', lit, ' ', ' '],
-                    [' ', ' ', ' ', ' '],
-                ],
-                2 => [
-                    [' ', ' ', ' ', ' '],
-                    [lit, lit, lit, ' '],
-                    [' ', lit, ' ', ' '],
-                    [' ', ' ', ' ', ' '],
-                ],
-                3 => [
-                    [' ', lit, ' ', ' '],
-                    [lit, lit, ' ', ' '],
-                    [' ', lit, ' ', ' '],
-                    [' ', ' ', ' ', ' '],
-                ],
-                _ => default,
-            },
-            'D' => match rot {
-                0 => [
-                    [lit, ' ', ' ', ' '],
-                    [lit, lit, lit, ' '],
-                    [' ', ' ', ' ', ' '],
-                    [' ', ' ', ' ', ' '],
-                ],
-                1 => [
-                    [' ', lit, lit, ' '],
-                    [' ', lit, ' ', ' '],
-                    [' ', lit, ' ', ' '],
-                    [' ', ' ', ' ', ' '],
-                ],
-                2 => [
-                    [' ', ' ', ' ', ' '],
-                    [lit, lit, lit, ' '],
-                    [' ', ' ', lit, ' '],
-                    [' ', ' ', ' ', ' '],
-                ],
-                3 => [
-                    [' ', lit, ' ', ' '],
-                    [' ', lit, ' ', ' '],
-                    [lit, lit, ' ', ' '],
-                    [' ', ' ', ' ', ' '],
-                ],
-                _ => default,
-            },
-            'R' => match rot {
-                0 => [
-                    [lit, lit, ' ', ' '],
-                    [' ', lit, lit, ' '],
-                    [' ', ' ', ' ', ' '],
-                    [' ', ' ', ' ', ' '],
-                ],
-                1 => [
-                    [' ', ' ', lit, ' '],
-                    [' ', lit, lit, ' '],
-                    [' ', lit, ' ', ' '],
-                    [' ', ' ', ' ', ' '],
-                ],
-                2 => [
-                    [' ', ' ', ' ', ' '],
-                    [lit, lit, ' ', ' '],
-                    [' ', lit, lit, ' '],
-                    [' ', ' ', ' ', ' '],
-                ],
-                3 => [
-                    [' ', lit, ' ', ' '],
-                    [lit, lit, ' ', ' '],
-                    [lit, ' ', ' ', ' '],
-                    [' ', ' ', ' ', ' '],
-                ],
-                _ => default,
-            },
-            'O' => match rot {
-                0 => [
-                    [' ', ' ', lit, ' '],
-                    [lit, lit, lit, ' '],
-                    [' ', ' ', ' ', ' '],
-                    [' ', ' ', ' ', ' '],
-                ],
-                1 => [
-                    [' ', lit, ' ', ' '],
-                    [' ', lit, ' ', ' '],
-                    [' ', lit, lit, ' '],
-                    [' ', ' ', ' ', ' '],
-                ],
-                2 => [
-                    [' ', ' ', ' ', ' '],
-                    [lit, lit, lit, ' '],
-                    [lit, ' ', ' ', ' '],
-                    [' ', ' ', ' ', ' '],
-                ],
-                3 => [
-                    [lit, lit, ' ', ' '],
-                    [' ', lit, ' ', ' '],
-                    [' ', lit, ' ', ' '],
-                    [' ', ' ', ' ', ' '],
-                ],
-                _ => default,
-            },
-            'G' => match rot {
-                0 => [
-                    [' ', lit, lit, ' '],
-                    [lit, lit, ' ', ' '],
-                    [' ', ' ', ' ', ' '],
-                    [' ', ' ', ' ', ' '],
-                ],
-                1 => [
-                    [' ', lit, ' ', ' '],
-                    [' ', lit, lit, ' '],
-                    [' ', ' ', lit, ' '],
-                    [' ', ' ', ' ', ' '],
-                ],
-                2 => [
-                    [' ', ' ', ' ', ' '],
-                    [' ', lit, lit, ' '],
-                    [lit, lit, ' ', ' '],
-                    [' ', ' ', ' ', ' '],
-                ],
-                3 => [
-                    [lit, ' ', ' ', ' '],
-                    [lit, lit, ' ', ' '],
-                    [' ', lit, ' ', ' '],
-                    [' ', ' ', ' ', ' '],
-                ],
-                _ => default,
-            },
-            _ => default,
-        }
-    }
+// cells are smaller on big boards so the board fits in 600x600
+fn cell_size(field: &GameField) -> u32 {
+    (600 / field.visible_height().max(field.width()) as u32).min(30)
 }
 
-fn restart(field: &mut GameField, piece: &mut Piece, score: &mut i32, lines: &mut i32, seconds: &mut f64, preview_piece: &mut Piece) {
-    for y in 0..field.len() {
-        for x in 0..field[y].len() {
-            field[y][x] = ' ';
-        }
-    }
-    *piece = Piece::new();
-    *preview_piece = Piece::new();
-    *score = 0;
-    *lines = 0;
-    *seconds = 0.;
+fn window_size(field: &GameField) -> (u32, u32) {
+    let cell = cell_size(field);
+    (7 + cell*field.width() as u32 + 7 + 30*4 + 7, 7 + 30*20 + 7)
 }
 
 pub fn main() {
-    // const
-    let framerate = 60;
-    let window_width = 7 + 30*10 + 7 + 30*4 + 7;
-    let window_height = 7 + 30*20 + 7;
     let bg_color = Color::RGB(0, 0, 0);
     let fg_color = Color::RGB(204, 204, 204);
     let hl_color = Color::RGB(204, 255, 136);
     let at_color = Color::RGB(204, 40, 40);
 
+    // render synced to display refresh rate unless started with --no-vsync
+    let vsync = !std::env::args().any(|arg| arg == "--no-vsync");
+    // pieces from the file given with --pieces, guideline tetrominoes by default
+    let args: Vec<String> = std::env::args().collect();
+    let pieces_path = args.iter().position(|arg| arg == "--pieces").and_then(|i| args.get(i + 1));
+    let piece_set = match pieces_path {
+        Some(path) => PieceSet::load(path).unwrap_or_else(|err| {
+            eprintln!("can't load pieces: {}", err);
+            std::process::exit(1);
+        }),
+        None => PieceSet::tetromino(),
+    };
+    // records are kept per pieces file
+    let pieces_name = pieces_path.and_then(|path| Path::new(path).file_stem()).map_or("tetromino".to_string(), |stem| stem.to_string_lossy().into_owned());
+    // longest real time simulated in one frame, the rest is dropped after window was stuck
+    let max_frame_time = Duration::from_millis(250);
+
     // mut
-    let mut tick_once_per_frames;
-    let mut frames_to_tick = 0;
     let mut state = GameState::Menu;
-    let mut field: GameField = [[' '; 10]; 20];
-    let mut preview_piece = Piece::new();
-    let mut piece = Piece::new();
-    let mut level = 1;
-    let mut score = 0;
-    let mut lines = 0;
-    let mut seconds = 0.;
+    let mut config = Config::default();
+    let mut engine = Engine::with_piece_set(config, piece_set.clone());
+    let (mut window_width, window_height) = window_size(engine.field());
+    let mut show_ghost = true;
+    let mut inputs = Vec::new();
+    let mut records = Records::load(RECORDS_PATH);
+    // personal best the finished game is compared to and whether the game beat it
+    let mut compared_splits = None;
+    let mut new_best = false;
 
     // sdl stuff
     let sdl_context = sdl2::init().unwrap();
@@ -458,7 +170,12 @@ pub fn main() {
     let mut window = video_subsystem.window("tetris", window_width, window_height);
     window.resizable();
     let window_builder = window.build().unwrap();
-    let mut canvas = window_builder.into_canvas().build().unwrap();
+    let mut canvas_builder = window_builder.into_canvas();
+    if vsync {
+        canvas_builder = canvas_builder.present_vsync();
+    }
+    let mut canvas = canvas_builder.build().unwrap();
+    canvas.set_blend_mode(BlendMode::Blend);
     let texture_creator = canvas.texture_creator();
     let ttf_context = sdl2::ttf::init().unwrap();
 
@@ -469,28 +186,31 @@ pub fn main() {
     // loading textures
     let grid_texture = load_texture(&texture_creator, "/grid.png");
     let gray_piece_texture = load_texture(&texture_creator, "/none.png");
-    let yellow_piece_texture = load_texture(&texture_creator, "/yellow.png");
-    let cyan_piece_texture = load_texture(&texture_creator, "/cyan.png");
-    let purple_piece_texture = load_texture(&texture_creator, "/purple.png");
-    let deep_purple_piece_texture = load_texture(&texture_creator, "/deep_purple.png");
-    let red_piece_texture = load_texture(&texture_creator, "/red.png");
-    let orange_piece_texture = load_texture(&texture_creator, "/orange.png");
-    let green_piece_texture = load_texture(&texture_creator, "/green.png");
+    let title_texture = load_texture(&texture_creator, "/green.png");
+    let garbage_texture = load_texture(&texture_creator, "/garbage.png");
 
     // select texture function
-    let get_texture = |ch: char| match ch {
-        'N' => &gray_piece_texture,
-        'Y' => &yellow_piece_texture,
-        'C' => &cyan_piece_texture,
-        'P' => &purple_piece_texture,
-        'D' => &deep_purple_piece_texture,
-        'R' => &red_piece_texture,
-        'O' => &orange_piece_texture,
-        'G' => &green_piece_texture,
-        _ => &gray_piece_texture,
-    };
+    // textures of a custom set may be missing, reported like a broken pieces file
+    let piece_textures: Vec<Texture> = piece_set.kinds().iter().map(|&kind| {
+        let def = piece_set.get(kind);
+        texture_creator.load_texture(format!("./assets/{}", def.texture)).unwrap_or_else(|err| {
+            eprintln!("can't load texture of piece {}: {}: {}", def.name, def.texture, err);
+            std::process::exit(1);
+        })
+    }).collect();
+    let get_texture = |kind: PieceKind| &piece_textures[kind.index()];
+
+    // next queue slots, first piece full size, the rest smaller until the longest queue fits above the panel text
+    let next_slot = preview_slot_height(&piece_set, 30) + 8;
+    let mut small_cell = 15;
+    while small_cell > 1 && 7 + next_slot + (MAX_NEXT_COUNT as i32 - 1) * (preview_slot_height(&piece_set, small_cell) + 8) > 280 {
+        small_cell -= 1;
+    }
+    let small_slot = preview_slot_height(&piece_set, small_cell) + 8;
 
     let mut event_pump = sdl_context.event_pump().unwrap();
+    let mut previous_frame = Instant::now();
+    let mut accumulator = Duration::ZERO;
     'running: loop {
         // process controls
         for event in event_pump.poll_iter() {
@@ -499,191 +219,326 @@ pub fn main() {
                 Event::KeyDown { keycode: Some(Keycode::Num4), .. } => {
                     break 'running
                 },
-                Event::KeyDown { keycode: Some(Keycode::Num1), .. } => {
-                    match state {
-                        GameState::Death => (),
-                        _ => state = GameState::Play,
-                    }
+                Event::KeyDown { keycode: Some(Keycode::Num1), .. } if !matches!(state, GameState::Death | GameState::Finish) => {
+                    state = GameState::Play;
                 },
                 Event::KeyDown { keycode: Some(Keycode::Num2), .. } => {
-                    restart(&mut field, &mut piece, &mut score, &mut lines, &mut seconds, &mut preview_piece);
+                    engine = Engine::with_piece_set(config, piece_set.clone());
+                    window_width = window_size(engine.field()).0;
+                    canvas.window_mut().set_size(window_width, window_height).unwrap();
                     state = GameState::Play;
                 },
-                Event::KeyDown { keycode: Some(Keycode::Num3), .. } => {
-                    match state {
-                        GameState::Death => (),
-                        _ => state = GameState::Menu,
-                    }
+                Event::KeyDown { keycode: Some(Keycode::Num3), .. } if !matches!(state, GameState::Death | GameState::Finish) => {
+                    state = GameState::Menu;
                 },
-                Event::KeyDown { keycode: Some(Keycode::Z), .. } => {
-                    match state {
-                        GameState::Play => piece.rotate(&field, -1),
-                        _ => (),
-                    }
+                Event::KeyDown { keycode: Some(Keycode::P), repeat: false, .. } if matches!(state, GameState::Play) => {
+                    state = GameState::Pause;
                 },
-                Event::KeyDown { keycode: Some(Keycode::X), .. } => {
-                    match state {
-                        GameState::Play => piece.rotate(&field, 1),
-                        _ => (),
-                    }
+                Event::KeyDown { keycode: Some(Keycode::P), repeat: false, .. } if matches!(state, GameState::Pause) => {
+                    state = GameState::Play;
                 },
-                Event::KeyDown { keycode: Some(Keycode::Left), .. } => {
-                    match state {
-                        GameState::Play => {
-                            piece.move_x(-1, &field);
-                        },
-                        _ => (),
-                    }
+                // don't let the game run while nobody is looking
+                Event::Window { win_event: WindowEvent::FocusLost | WindowEvent::Minimized, .. } if matches!(state, GameState::Play) => {
+                    state = GameState::Pause;
                 },
-                Event::KeyDown { keycode: Some(Keycode::Right), .. } => {
-                    match state {
-                        GameState::Play => {
-                            piece.move_x(1, &field);
-                        },
-                        _ => (),
-                    }
+                Event::KeyDown { keycode: Some(Keycode::Num5), .. } if matches!(state, GameState::Menu) => {
+                    config.randomizer = config.randomizer.cycle();
+                },
+                Event::KeyDown { keycode: Some(Keycode::Num6), .. } if matches!(state, GameState::Menu) => {
+                    config.next_count = config.next_count % MAX_NEXT_COUNT + 1;
+                },
+                Event::KeyDown { keycode: Some(Keycode::Num7), .. } if matches!(state, GameState::Menu) => {
+                    show_ghost = !show_ghost;
+                },
+                Event::KeyDown { keycode: Some(Keycode::Num8), .. } if matches!(state, GameState::Menu) => {
+                    let i = BOARD_SIZES.iter().position(|&size| size == (config.width, config.height)).unwrap_or(0);
+                    (config.width, config.height) = BOARD_SIZES[(i + 1) % BOARD_SIZES.len()];
                 },
-                Event::KeyDown { keycode: Some(Keycode::Down), .. } => {
-                    match state {
-                        GameState::Play => frames_to_tick = 1,
-                        _ => (),
+                Event::KeyDown { keycode: Some(Keycode::Num9), .. } if matches!(state, GameState::Menu) => {
+                    config.mode = config.mode.cycle();
+                },
+                Event::KeyDown { keycode: Some(Keycode::Num0), .. } if matches!(state, GameState::Menu) => {
+                    config.start_level = config.start_level % MAX_START_LEVEL + 1;
+                },
+                Event::KeyDown { keycode: Some(Keycode::E), .. } if matches!(state, GameState::Menu) => {
+                    config.endless = !config.endless;
+                },
+                Event::KeyDown { keycode: Some(Keycode::L), .. } if matches!(state, GameState::Menu) => {
+                    config.level_progression = config.level_progression.cycle();
+                },
+                Event::KeyDown { keycode: Some(Keycode::D), .. } if matches!(state, GameState::Menu) => {
+                    let i = DIG_ROWS.iter().position(|&rows| rows == config.dig_rows).unwrap_or(0);
+                    config.dig_rows = DIG_ROWS[(i + 1) % DIG_ROWS.len()];
+                },
+                Event::KeyDown { keycode: Some(keycode), repeat: false, .. } if matches!(state, GameState::Play) => {
+                    if let Some(button) = button_for_key(keycode) {
+                        inputs.push(Input::Press(button));
                     }
                 },
-                Event::KeyDown { keycode: Some(Keycode::Up), .. } => {
-                    match state {
-                        GameState::Play => {
-                            piece.drop_down(&field);
-                            frames_to_tick = 1;
-                        },
-                        _ => (),
+                // releases are kept until the next step so no button stays held after leaving a menu
+                Event::KeyUp { keycode: Some(keycode), .. } => {
+                    if let Some(button) = button_for_key(keycode) {
+                        inputs.push(Input::Release(button));
                     }
                 },
                 _ => {}
             }
         }
 
+        // run fixed simulation ticks for the real time passed since the previous frame
+        let now = Instant::now();
+        accumulator = (accumulator + (now - previous_frame)).min(max_frame_time);
+        previous_frame = now;
+        if matches!(state, GameState::Play) {
+            while accumulator >= TICK {
+                engine.step(&inputs);
+                inputs.clear();
+                accumulator -= TICK;
+            }
+            if engine.is_finished() {
+                state = GameState::Finish;
+                let rules = sprint_rules(engine.config(), &pieces_name);
+                compared_splits = records.sprint(&rules).cloned();
+                new_best = engine.config().mode == Mode::Sprint && records.submit_sprint(&rules, &engine.split_millis());
+                if new_best {
+                    if let Err(err) = records.save(RECORDS_PATH) {
+                        eprintln!("can't save records: {}", err);
+                    }
+                }
+            } else if engine.is_game_over() {
+                state = GameState::Death;
+            }
+        } else {
+            accumulator = Duration::ZERO;
+        }
+
         // clear canvas
         canvas.set_draw_color(bg_color);
         canvas.clear();
 
         match state {
             GameState::Menu => {
-                write_tetris_by_textures(&mut canvas, &green_piece_texture);
-
-                set_text(&mut canvas, &font, &texture_creator, hl_color, "1: play", Rect::new(7, 140, window_width - 14, 60));
-                set_text(&mut canvas, &font, &texture_creator, fg_color, "2: restart", Rect::new(7, 140+60, window_width - 14, 60));
-                set_text(&mut canvas, &font, &texture_creator, fg_color, "3: menu", Rect::new(7, 140+120, window_width - 14, 60));
-                set_text(&mut canvas, &font, &texture_creator, fg_color, "4: exit", Rect::new(7, 140+180, window_width - 14, 60));
-
+                write_tetris_by_textures(&mut canvas, &title_texture);
+
+                let menu = [
+                    "1: continue".to_string(),
+                    "2: new game".to_string(),
+                    "3: menu".to_string(),
+                    "4: exit".to_string(),
+                    format!("5: pieces: {}", config.randomizer.name()),
+                    format!("6: next pieces: {}", config.next_count),
+                    format!("7: ghost: {}", if show_ghost { "on" } else { "off" }),
+                    format!("8: board: {}x{}", config.width, config.height),
+                    format!("9: mode: {}", config.mode.name()),
+                    format!("0: start level: {}", config.start_level),
+                    format!("e: endless marathon: {}", if config.endless { "on" } else { "off" }),
+                    format!("d: dig rows: {}", config.dig_rows),
+                    format!("l: level up: {}", config.level_progression.name()),
+                ];
+                for (i, text) in menu.iter().enumerate() {
+                    let color = if i == 0 { hl_color } else { fg_color };
+                    set_text(&mut canvas, &font, &texture_creator, color, text, Rect::new(7, 140 + i as i32 * 36, window_width - 14, 36));
+                }
+            },
+            GameState::Pause => {
+                set_text(&mut canvas, &font, &texture_creator, hl_color, "PAUSE", Rect::new(7, 40, window_width - 14, 60));
+                set_text(&mut canvas, &font, &texture_creator, fg_color, &format!("score: {}", engine.score()), Rect::new((window_width / 2 - 80) as i32, 140, 140, 30));
+                set_text(&mut canvas, &font, &texture_creator, fg_color, &format!("time: {}", format_time(engine.seconds())), Rect::new((window_width / 2 - 80) as i32, 140+30, 140, 30));
+                set_text(&mut canvas, &font, &texture_creator, hl_color, "1: continue", Rect::new(7, 300, window_width - 14, 60));
+                set_text(&mut canvas, &font, &texture_creator, fg_color, "2: restart", Rect::new(7, 300+60, window_width - 14, 60));
+                set_text(&mut canvas, &font, &texture_creator, fg_color, "3: to menu", Rect::new(7, 300+120, window_width - 14, 60));
             },
             GameState::Death => {
                 set_text(&mut canvas, &font, &texture_creator, at_color, "Death", Rect::new(7, 40, window_width - 14, 60));
-                set_text(&mut canvas, &font, &texture_creator, fg_color, "level: 1", Rect::new((window_width / 2 - 80) as i32, 140+0*30, 140, 30));
-                set_text(&mut canvas, &font, &texture_creator, fg_color, &format!("score: {}", score), Rect::new((window_width / 2 - 80) as i32, 140+1*30, 140, 30));
-                set_text(&mut canvas, &font, &texture_creator, fg_color, &format!("lines: {}", lines), Rect::new((window_width / 2 - 80) as i32, 140+2*30, 140, 30));
-                set_text(&mut canvas, &font, &texture_creator, fg_color, &format!("time: {:.1}", seconds), Rect::new((window_width / 2 - 80) as i32, 140+3*30, 140, 30));
+                if let Some(top_out) = engine.top_out() {
+                    set_text(&mut canvas, &font, &texture_creator, at_color, top_out.name(), Rect::new((window_width / 2 - 80) as i32, 100, 140, 30));
+                }
+                set_text(&mut canvas, &font, &texture_creator, fg_color, &format!("level: {}", engine.level()), Rect::new((window_width / 2 - 80) as i32, 140, 140, 30));
+                set_text(&mut canvas, &font, &texture_creator, fg_color, &format!("score: {}", engine.score()), Rect::new((window_width / 2 - 80) as i32, 140+30, 140, 30));
+                set_text(&mut canvas, &font, &texture_creator, fg_color, &format!("lines: {}", engine.lines()), Rect::new((window_width / 2 - 80) as i32, 140+2*30, 140, 30));
+                set_text(&mut canvas, &font, &texture_creator, fg_color, &format!("time: {}", format_millis(engine.millis())), Rect::new((window_width / 2 - 80) as i32, 140+3*30, 140, 30));
+                if let Some(grade) = engine.grade() {
+                    set_text(&mut canvas, &font, &texture_creator, hl_color, &format!("grade: {}", grade), Rect::new((window_width / 2 - 80) as i32, 140+4*30, 140, 30));
+                }
                 set_text(&mut canvas, &font, &texture_creator, hl_color, "2: restart", Rect::new(7, 300+60, window_width - 14, 60));
                 set_text(&mut canvas, &font, &texture_creator, fg_color, "4: exit", Rect::new(7, 300+120, window_width - 14, 60));
             },
+            GameState::Finish => {
+                let title = if engine.config().mode == Mode::Marathon { "Win" } else { "Finish" };
+                set_text(&mut canvas, &font, &texture_creator, hl_color, title, Rect::new(7, 40, window_width - 14, 60));
+                if new_best {
+                    set_text(&mut canvas, &font, &texture_creator, hl_color, "personal best", Rect::new((window_width / 2 - 80) as i32, 100, 140, 30));
+                }
+                if engine.config().mode.time_limit_ms().is_some() {
+                    // score attack results with every type of clear that happened
+                    set_text(&mut canvas, &font, &texture_creator, fg_color, &format!("score: {}", engine.score()), Rect::new((window_width / 2 - 80) as i32, 140, 140, 25));
+                    set_text(&mut canvas, &font, &texture_creator, fg_color, &format!("lines: {}", engine.lines()), Rect::new((window_width / 2 - 80) as i32, 140+25, 140, 25));
+                    let clears = engine.clear_stats().entries();
+                    for (i, (name, count)) in clears.iter().filter(|&&(_, count)| count > 0).enumerate() {
+                        set_text(&mut canvas, &font, &texture_creator, fg_color, &format!("{}: {}", name, count), Rect::new((window_width / 2 - 80) as i32, 140+(i as i32+2)*25, 140, 25));
+                    }
+                } else if let Some(grade) = engine.grade() {
+                    // grade revealed with the time of every section
+                    set_text(&mut canvas, &font, &texture_creator, hl_color, &format!("grade: {}", grade), Rect::new((window_width / 2 - 80) as i32, 140, 140, 25));
+                    set_text(&mut canvas, &font, &texture_creator, fg_color, &format!("time: {}", format_millis(engine.millis())), Rect::new((window_width / 2 - 80) as i32, 140+25, 140, 25));
+                    for (i, &ms) in engine.section_millis().iter().enumerate() {
+                        let text = format!("{}-{}: {}", i * 100, i * 100 + 99, format_millis(ms));
+                        set_text(&mut canvas, &font, &texture_creator, fg_color, &text, Rect::new((window_width / 2 - 80) as i32, 140+(i as i32+2)*25, 140, 25));
+                    }
+                } else if engine.config().mode == Mode::Dig {
+                    set_text(&mut canvas, &font, &texture_creator, fg_color, &format!("time: {}", format_millis(engine.millis())), Rect::new((window_width / 2 - 80) as i32, 140, 140, 30));
+                    set_text(&mut canvas, &font, &texture_creator, fg_color, &format!("pieces: {}", engine.pieces()), Rect::new((window_width / 2 - 80) as i32, 140+30, 140, 30));
+                } else if let Some(split_lines) = engine.config().mode.split_lines() {
+                    set_text(&mut canvas, &font, &texture_creator, fg_color, &format!("time: {}", format_millis(engine.millis())), Rect::new((window_width / 2 - 80) as i32, 140, 140, 30));
+                    for (i, &ms) in engine.split_millis().iter().enumerate() {
+                        let best = compared_splits.as_ref().and_then(|splits| splits.get(i).copied());
+                        let (text, color) = format_split((i as i32 + 1) * split_lines, ms, best, (fg_color, hl_color, at_color));
+                        set_text(&mut canvas, &font, &texture_creator, color, &text, Rect::new((window_width / 2 - 80) as i32, 140+(i as i32+1)*30, 140, 30));
+                    }
+                } else {
+                    set_text(&mut canvas, &font, &texture_creator, fg_color, &format!("level: {}", engine.level()), Rect::new((window_width / 2 - 80) as i32, 140, 140, 30));
+                    set_text(&mut canvas, &font, &texture_creator, fg_color, &format!("score: {}", engine.score()), Rect::new((window_width / 2 - 80) as i32, 140+30, 140, 30));
+                    set_text(&mut canvas, &font, &texture_creator, fg_color, &format!("lines: {}", engine.lines()), Rect::new((window_width / 2 - 80) as i32, 140+2*30, 140, 30));
+                    set_text(&mut canvas, &font, &texture_creator, fg_color, &format!("time: {}", format_millis(engine.millis())), Rect::new((window_width / 2 - 80) as i32, 140+3*30, 140, 30));
+                }
+                set_text(&mut canvas, &font, &texture_creator, hl_color, "2: restart", Rect::new(7, 300+180, window_width - 14, 60));
+                set_text(&mut canvas, &font, &texture_creator, fg_color, "4: exit", Rect::new(7, 300+240, window_width - 14, 60));
+            },
             GameState::Play => {
+                let field = engine.field();
+                let cell = cell_size(field);
+                let board_width = cell * field.width() as u32;
+                let panel_x = 7 + board_width as i32 + 7;
+                let visible_top = field.visible_top();
+                let cell_rect = |x: i32, y: i32| Rect::new(7 + x * cell as i32, 7 + (y - visible_top) * cell as i32, cell, cell);
+
                 // side panel
                 canvas.set_draw_color(Color::RGB(30, 30, 30));
-                canvas.fill_rect(Rect::new(7+10*30+7, 0, 30*4 + 7, window_height)).unwrap();
-
-                canvas.copy(&grid_texture, None, Rect::new(7, 7, 30*10, 30*20));
-
-                set_text(&mut canvas, &font, &texture_creator, fg_color, &format!("level: {}", level), Rect::new(7+10*30+7, 200+0*30, 4*30, 30));
-                set_text(&mut canvas, &font, &texture_creator, fg_color, &format!("score: {}", score), Rect::new(7+10*30+7, 200+1*30, 4*30, 30));
-                set_text(&mut canvas, &font, &texture_creator, fg_color, &format!("lines: {}", lines), Rect::new(7+10*30+7, 200+2*30, 4*30, 30));
-                set_text(&mut canvas, &font, &texture_creator, fg_color, &format!("time: {:.1}", seconds), Rect::new(7+10*30+7, 200+3*30, 4*30, 30));
+                canvas.fill_rect(Rect::new(panel_x, 0, 30*4 + 7, window_height)).unwrap();
 
-                let preview_body = preview_piece.body();
-                for y in 0..4 {
-                    for x in 0..4 {
-                        if preview_body[y][x] != ' ' {
-                            canvas.copy(get_texture(preview_body[y][x]), None, Some(Rect::new(7+(10 + x as i32) * 30+7, 7+(3 + y as i32)*30, 30, 30))).unwrap();
-                        }
+                // grid texture is 10x20 cells of 30px, tile its first cell for other sizes
+                for y in visible_top..field.height() as i32 {
+                    for x in 0..field.width() as i32 {
+                        canvas.copy(&grid_texture, Rect::new(0, 0, 30, 30), cell_rect(x, y)).unwrap();
                     }
                 }
 
-                // game field
-                level = lines / 30 + 1;
-                tick_once_per_frames = 50 / level;
-
-                if frames_to_tick <= 0 {
-                    frames_to_tick = tick_once_per_frames;
-
-                    // place piece if need
-                    if !piece.is_move_down_awailable(&field) {
-                        piece.put_on_a_field(&mut field, true);
-                        piece = preview_piece;
-                        preview_piece = Piece::new();
+                if let (Some(goal), Some(split_lines)) = (engine.config().goal_lines(), engine.config().mode.split_lines()) {
+                    // race against the clock, splits compared to the personal best
+                    let best_splits = records.sprint(&sprint_rules(engine.config(), &pieces_name));
+                    set_text(&mut canvas, &font, &texture_creator, fg_color, &format!("lines: {}/{}", engine.lines(), goal), Rect::new(panel_x, 280, 4*30, 30));
+                    set_text(&mut canvas, &font, &texture_creator, fg_color, &format!("time: {}", format_millis(engine.millis())), Rect::new(panel_x, 280+30, 4*30, 30));
+                    for (i, &ms) in engine.split_millis().iter().enumerate() {
+                        let best = best_splits.and_then(|splits| splits.get(i).copied());
+                        let (text, color) = format_split((i as i32 + 1) * split_lines, ms, best, (fg_color, hl_color, at_color));
+                        set_text(&mut canvas, &font, &texture_creator, color, &text, Rect::new(panel_x, 280+(i as i32+2)*30, 4*30, 30));
                     }
+                } else {
+                    let level = match engine.config().mode {
+                        // level and where the current section stops
+                        Mode::Master => format!("level: {}/{}", engine.level(), (engine.level() + engine.leveling().lines_to_next_level()).min(MASTER_MAX_LEVEL)),
+                        _ => format!("level: {}", engine.level()),
+                    };
+                    set_text(&mut canvas, &font, &texture_creator, fg_color, &level, Rect::new(panel_x, 280, 4*30, 30));
+                    set_text(&mut canvas, &font, &texture_creator, fg_color, &format!("score: {}", engine.score()), Rect::new(panel_x, 280+30, 4*30, 30));
+                    let lines = match engine.config().goal_lines() {
+                        _ if engine.config().mode == Mode::Dig => format!("garbage: {}", engine.garbage_remaining()),
+                        Some(goal) => format!("lines: {}/{}", engine.lines(), goal),
+                        None => format!("lines: {}", engine.lines()),
+                    };
+                    set_text(&mut canvas, &font, &texture_creator, fg_color, &lines, Rect::new(panel_x, 280+2*30, 4*30, 30));
+                    let time = match engine.remaining_millis() {
+                        Some(ms) => format!("time left: {}", format_millis(ms)),
+                        None => format!("time: {}", format_millis(engine.millis())),
+                    };
+                    set_text(&mut canvas, &font, &texture_creator, fg_color, &time, Rect::new(panel_x, 280+3*30, 4*30, 30));
+                    set_text(&mut canvas, &font, &texture_creator, fg_color, &format!("combo: {}", engine.scoring().combo().max(0)), Rect::new(panel_x, 280+4*30, 4*30, 30));
+                    if engine.scoring().is_back_to_back() {
+                        set_text(&mut canvas, &font, &texture_creator, hl_color, "back to back", Rect::new(panel_x, 280+5*30, 4*30, 30));
+                    }
+                }
 
-                    // remove filled lines
-                    let mut filled_lines = 0;
-                    for y in 0..field.len() {
-                        let mut is_full = true;
-                        for x in 0..field[y].len() {
-                            if field[y][x] != 'N' {
-                                is_full = false;
-                                break;
-                            }
-                        }
-                        if is_full {
-                            for i in (1..y+1).rev() {
-                                let temp = field[i];
-                                field[i] = field[i-1];
-                                field[i-1] = temp;
-                            }
-                            for i in 0..field[0].len() {
-                                field[0][i] = ' ';
-                            }
-                            filled_lines += 1;
-                        }
+                // next queue, first piece full size, the rest at about half size
+                for (i, &kind) in engine.next_pieces().iter().enumerate() {
+                    match i {
+                        0 => draw_piece_preview(&mut canvas, &get_texture, engine.piece_set(), kind, panel_x, 7, 30),
+                        _ => draw_piece_preview(&mut canvas, &get_texture, engine.piece_set(), kind, panel_x+15, 7+next_slot+(i as i32-1)*small_slot, small_cell),
                     }
+                }
 
-                    lines += filled_lines;
-                    score += match filled_lines {
-                        1 => 40,
-                        2 => 100,
-                        3 => 300,
-                        4 => 1200,
-                        _ => 0,
-                    };
+                // hold piece is gray when it can't be swapped
+                set_text(&mut canvas, &font, &texture_creator, fg_color, "hold:", Rect::new(panel_x, 280+6*30+10, 2*30, 30));
+                if let Some(kind) = engine.hold_piece() {
+                    let get_hold_texture = |kind| if engine.can_hold() { get_texture(kind) } else { &gray_piece_texture };
+                    draw_piece_preview(&mut canvas, &get_hold_texture, engine.piece_set(), kind, panel_x, 280+7*30+10, 30);
+                }
 
-                    // die if reach top of game field
-                    for x in 0..field[0].len() {
-                        if field[0][x] == 'N' {
-                            state = GameState::Death;
-                        }
+                // display field
+                for (y, row) in field.rows().iter().enumerate().skip(visible_top as usize) {
+                    for (x, &cell) in row.iter().enumerate() {
+                        let texture = match cell {
+                            Cell::Empty => continue,
+                            Cell::Locked(kind) => get_texture(kind),
+                            Cell::Garbage => &garbage_texture,
+                        };
+                        canvas.copy(texture, None, Some(cell_rect(x as i32, y as i32))).unwrap();
                     }
+                }
 
-                    piece.force_move_y(1);
-                } else {
-                    frames_to_tick -= 1;
+                // filled lines flash before they are removed
+                for y in engine.clearing_lines() {
+                    canvas.set_draw_color(Color::RGBA(255, 255, 255, 120));
+                    canvas.fill_rect(Rect::new(7, 7 + (y as i32 - visible_top) * cell as i32, board_width, cell)).unwrap();
                 }
 
-                piece.put_on_a_field(&mut field, false);
+                let falling = engine.phase() == Phase::Falling;
 
-                // disPlay field
-                for y in 0..field.len() {
-                    for x in 0..field[y].len() {
-                        if field[y][x] == ' ' {
+                // display ghost piece, piece texture darkened by translucent background color
+                if show_ghost && falling {
+                    let ghost = engine.ghost_piece();
+                    for (x, y) in ghost.cells() {
+                        if y < visible_top {
                             continue
                         }
-                        let texture = get_texture(field[y][x]);
+                        canvas.copy(get_texture(ghost.kind), None, Some(cell_rect(x, y))).unwrap();
+                        canvas.set_draw_color(Color::RGBA(0, 0, 0, 170));
+                        canvas.fill_rect(cell_rect(x, y)).unwrap();
+                    }
+                }
 
-                        canvas.copy(texture, None, Some(Rect::new(7+x as i32 * 30, 7+y as i32 * 30, 30, 30))).unwrap();
+                // display falling piece
+                if falling {
+                    let piece = engine.piece();
+                    for (x, y) in piece.cells() {
+                        if y < visible_top {
+                            continue
+                        }
+                        canvas.copy(get_texture(piece.kind), None, Some(cell_rect(x, y))).unwrap();
                     }
                 }
 
-                seconds += 1./60.;
+                match engine.phase() {
+                    Phase::Ready => set_text(&mut canvas, &font, &texture_creator, hl_color, "READY", Rect::new(7, 7+5*30, board_width, 90)),
+                    Phase::Go => set_text(&mut canvas, &font, &texture_creator, hl_color, "GO", Rect::new(7, 7+5*30, board_width, 90)),
+                    _ => (),
+                }
+
+                if let Some(clear) = engine.callout() {
+                    if let Some(name) = clear.name() {
+                        set_text(&mut canvas, &font, &texture_creator, hl_color, &name, Rect::new(7, 7+8*30, board_width, 60));
+                    }
+                    if clear.perfect_clear {
+                        set_text(&mut canvas, &font, &texture_creator, hl_color, "PERFECT", Rect::new(7, 7+2*30, board_width, 90));
+                        set_text(&mut canvas, &font, &texture_creator, hl_color, "CLEAR", Rect::new(7, 7+5*30, board_width, 90));
+                    }
+                }
             },
         }
 
 
         canvas.present();
-        std::thread::sleep(Duration::new(0, 1_000_000_000u32 / framerate));
+        if !vsync {
+            std::thread::sleep(TICK.saturating_sub(accumulator + previous_frame.elapsed()));
+        }
     }
 }
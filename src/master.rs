@@ -0,0 +1,298 @@
+// master mode rules modelled on the TGM series: section levels 0 to 999,
+// speed tables in frames of the 60Hz tick and a hidden grade
+
+// level where the game ends
+pub const MASTER_MAX_LEVEL: i32 = 999;
+pub const SECTION_LEVELS: i32 = 100;
+
+// section finished in this many ticks or faster raises the grade
+const FAST_SECTION_TICKS: u64 = 60 * 60;
+// every section must be at least this fast for the master grade
+const MASTER_SECTION_TICKS: u64 = 65 * 60;
+
+// (level, gravity in 1/256 rows per tick), gravity applies from the level on
+const GRAVITY: [(i32, u32); 30] = [
+    (0, 4),
+    (30, 6),
+    (35, 8),
+    (40, 10),
+    (50, 12),
+    (60, 16),
+    (70, 32),
+    (80, 48),
+    (90, 64),
+    (100, 80),
+    (120, 96),
+    (140, 112),
+    (160, 128),
+    (170, 144),
+    (200, 4),
+    (220, 32),
+    (230, 64),
+    (233, 96),
+    (236, 128),
+    (239, 160),
+    (243, 192),
+    (247, 224),
+    (251, 256),
+    (300, 512),
+    (330, 768),
+    (360, 1024),
+    (400, 1280),
+    (420, 1024),
+    (450, 768),
+    (500, 5120),
+];
+
+// rows per tick at `level`, 20G from level 500
+pub fn master_gravity(level: i32) -> f64 {
+    let gravity = GRAVITY.iter().rev().find(|&&(from, _)| level >= from).map_or(4, |&(_, gravity)| gravity);
+    gravity as f64 / 256.
+}
+
+// delays in ticks
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Delays {
+    // entry delay after a piece locks without clearing lines
+    pub are: u32,
+    // entry delay after lines are removed
+    pub line_are: u32,
+    pub das: u32,
+    pub lock: u32,
+    pub line_clear: u32,
+}
+
+// delays get shorter in later sections
+pub fn master_delays(level: i32) -> Delays {
+    let (are, line_are, das, lock, line_clear) = match level {
+        ..=499 => (25, 25, 14, 30, 40),
+        500..=599 => (25, 25, 8, 30, 25),
+        600..=699 => (25, 16, 8, 30, 16),
+        700..=799 => (16, 12, 8, 30, 12),
+        800..=899 => (12, 6, 8, 30, 6),
+        _ => (12, 6, 6, 17, 6),
+    };
+    Delays {
+        are,
+        line_are,
+        das,
+        lock,
+        line_clear,
+    }
+}
+
+// shown grade for every internal grade
+const GRADE_NAMES: [&str; 32] = [
+    "9", "8", "7", "6", "5", "4", "4", "3", "3", "2", "2", "2", "1", "1", "1", "S1", "S1", "S1", "S2", "S3", "S4", "S4", "S4", "S5", "S5",
+    "S6", "S6", "S7", "S7", "S8", "S8", "S9",
+];
+
+// ticks of active play per lost grade point for every internal grade
+const DECAY_TICKS: [u32; 32] = [
+    125, 80, 80, 50, 45, 45, 45, 40, 40, 40, 40, 40, 30, 30, 30, 20, 20, 20, 20, 20, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 10, 10,
+];
+
+// grade points for a single, double, triple and tetris
+fn clear_points(internal: usize, lines: i32) -> i32 {
+    let points = match internal {
+        0 => [10, 20, 40, 50],
+        1..=2 => [10, 20, 30, 40],
+        3 => [10, 15, 30, 40],
+        4 => [10, 15, 20, 40],
+        5 => [5, 15, 20, 30],
+        6 => [5, 10, 20, 30],
+        7..=9 => [5, 10, 15, 30],
+        _ => [2, 12, 13, 30],
+    };
+    points[(lines.clamp(1, 4) - 1) as usize]
+}
+
+// hidden grade, grade points come from clears and slowly decay while a piece is in play,
+// every 100 points is the next internal grade, fast sections give one more
+#[derive(Clone, Debug, Default)]
+pub struct Grading {
+    internal: usize,
+    points: i32,
+    decay_ticks: u32,
+    // ticks every finished section took
+    sections: Vec<u64>,
+}
+
+impl Grading {
+    pub fn new() -> Grading {
+        Grading::default()
+    }
+
+    // tick of active play without an ongoing combo
+    pub fn decay(&mut self) {
+        self.decay_ticks += 1;
+        if self.decay_ticks >= DECAY_TICKS[self.internal] {
+            self.decay_ticks = 0;
+            self.points = (self.points - 1).max(0);
+        }
+    }
+
+    // `combo` counts clears in a row before this one, doubles and more in a combo are worth more
+    pub fn line_clear(&mut self, lines: i32, combo: i32, level: i32) {
+        if lines <= 0 {
+            return;
+        }
+        let combo_bonus = if lines > 1 { 1. + 0.2 * combo.clamp(0, 9) as f64 } else { 1. };
+        let level_bonus = 1. + level as f64 / 250.;
+        self.points += (clear_points(self.internal, lines) as f64 * combo_bonus * level_bonus).ceil() as i32;
+        while self.points >= 100 {
+            self.points -= 100;
+            self.raise();
+        }
+    }
+
+    // section finished after `ticks` since the previous one
+    pub fn section(&mut self, ticks: u64) {
+        self.sections.push(ticks);
+        if ticks <= FAST_SECTION_TICKS {
+            self.raise();
+        }
+    }
+
+    fn raise(&mut self) {
+        if self.internal + 1 < GRADE_NAMES.len() {
+            self.internal += 1;
+            self.decay_ticks = 0;
+        }
+    }
+
+    // ticks of every finished section
+    pub fn sections(&self) -> &[u64] {
+        &self.sections
+    }
+
+    // master grade needs the highest grade, the whole game and no slow section
+    pub fn name(&self, finished: bool) -> &'static str {
+        let all_fast = self.sections.iter().all(|&ticks| ticks <= MASTER_SECTION_TICKS);
+        if finished && self.internal + 1 == GRADE_NAMES.len() && all_fast {
+            return "M";
+        }
+        GRADE_NAMES[self.internal]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::{LevelProgression, Leveling};
+    use crate::scoring::LineClear;
+
+    #[test]
+    fn gravity_reaches_20g_at_500() {
+        assert_eq!(master_gravity(0), 4. / 256.);
+        assert_eq!(master_gravity(199), 144. / 256.);
+        // drops back at 200 before climbing to 1G at 251
+        assert_eq!(master_gravity(200), 4. / 256.);
+        assert_eq!(master_gravity(251), 1.);
+        assert_eq!(master_gravity(499), 3.);
+        assert_eq!(master_gravity(500), 20.);
+        assert_eq!(master_gravity(999), 20.);
+    }
+
+    #[test]
+    fn delays_shorten_in_later_sections() {
+        assert_eq!(master_delays(0), master_delays(499));
+        assert_eq!(master_delays(0).line_clear, 40);
+        assert_eq!(master_delays(500).das, 8);
+        assert_eq!(master_delays(700).are, 16);
+        let last = master_delays(900);
+        assert_eq!((last.are, last.line_are, last.das, last.lock, last.line_clear), (12, 6, 6, 17, 6));
+        for level in (100..=900).step_by(100) {
+            let (before, after) = (master_delays(level - 1), master_delays(level));
+            assert!(after.are <= before.are && after.lock <= before.lock && after.line_clear <= before.line_clear);
+        }
+    }
+
+    #[test]
+    fn section_stops_until_lines_are_cleared() {
+        let mut leveling = Leveling::new(LevelProgression::Sections, 5, Some(MASTER_MAX_LEVEL));
+        assert_eq!(leveling.level(), 0);
+        for _ in 0..150 {
+            leveling.line_clear(LineClear::plain(0), false);
+        }
+        assert_eq!((leveling.level(), leveling.lines_to_next_level()), (99, 1));
+        leveling.line_clear(LineClear::plain(2), false);
+        assert_eq!(leveling.level(), 101);
+    }
+
+    #[test]
+    fn only_a_clear_reaches_999() {
+        let mut leveling = Leveling::new(LevelProgression::Sections, 0, Some(MASTER_MAX_LEVEL));
+        while leveling.level() < 990 {
+            leveling.line_clear(LineClear::plain(4), false);
+        }
+        for _ in 0..20 {
+            leveling.line_clear(LineClear::plain(0), false);
+        }
+        assert_eq!(leveling.level(), 998);
+        leveling.line_clear(LineClear::plain(4), false);
+        assert_eq!(leveling.level(), MASTER_MAX_LEVEL);
+    }
+
+    #[test]
+    fn clears_raise_the_grade() {
+        let mut grading = Grading::new();
+        assert_eq!(grading.name(false), "9");
+        // two tetrises at grade 9 are 100 points
+        grading.line_clear(4, 0, 0);
+        grading.line_clear(4, 0, 0);
+        assert_eq!(grading.name(false), "8");
+    }
+
+    #[test]
+    fn combo_and_level_are_worth_more() {
+        let mut plain = Grading::new();
+        plain.line_clear(2, 0, 0);
+        let mut combo = Grading::new();
+        combo.line_clear(2, 3, 0);
+        let mut high = Grading::new();
+        high.line_clear(2, 0, 500);
+        assert!(combo.points > plain.points);
+        assert!(high.points > plain.points);
+    }
+
+    #[test]
+    fn points_decay_while_playing() {
+        let mut grading = Grading::new();
+        grading.line_clear(1, 0, 0);
+        // 125 ticks per point at grade 9
+        for _ in 0..125 * 10 {
+            grading.decay();
+        }
+        assert_eq!(grading.points, 0);
+        for _ in 0..1000 {
+            grading.decay();
+        }
+        assert_eq!(grading.points, 0);
+    }
+
+    #[test]
+    fn fast_sections_raise_the_grade() {
+        let mut grading = Grading::new();
+        grading.section(FAST_SECTION_TICKS + 1);
+        assert_eq!(grading.name(false), "9");
+        grading.section(FAST_SECTION_TICKS);
+        assert_eq!(grading.name(false), "8");
+        assert_eq!(grading.sections(), &[FAST_SECTION_TICKS + 1, FAST_SECTION_TICKS]);
+    }
+
+    #[test]
+    fn master_grade_needs_s9_a_finished_game_and_no_slow_section() {
+        let mut grading = Grading::new();
+        for _ in 0..10 {
+            grading.section(FAST_SECTION_TICKS);
+        }
+        while grading.name(false) != "S9" {
+            grading.line_clear(4, 0, 999);
+        }
+        assert_eq!(grading.name(false), "S9");
+        assert_eq!(grading.name(true), "M");
+        grading.section(MASTER_SECTION_TICKS + 1);
+        assert_eq!(grading.name(true), "S9");
+    }
+}
@@ -0,0 +1,114 @@
+// game modes, what ends the game besides topping out
+
+use crate::config::LockReset;
+use crate::level::{guideline_gravity, LevelProgression};
+use crate::master::{MASTER_MAX_LEVEL, SECTION_LEVELS};
+
+// highest level a game can start at, also where marathon stops leveling
+pub const MAX_START_LEVEL: i32 = 15;
+
+// garbage rows to dig through that can be chosen
+pub const DIG_ROWS: [u32; 3] = [10, 18, 100];
+// most garbage rows on the board at once, the rest comes up as they are cleared
+pub const DIG_BOARD_ROWS: usize = 10;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Mode {
+    // 150 lines with level going up to 15, or play until top out when endless
+    Marathon,
+    // clear 40 lines as fast as possible
+    Sprint,
+    // score as much as possible in two minutes
+    Ultra,
+    // clear all garbage rows with as few pieces and as fast as possible
+    Dig,
+    // levels 0 to 999 getting up to 20G with shorter delays, graded at the end
+    Master,
+}
+
+impl Mode {
+    pub const ALL: [Mode; 5] = [Mode::Marathon, Mode::Sprint, Mode::Ultra, Mode::Dig, Mode::Master];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Mode::Marathon => "marathon",
+            Mode::Sprint => "sprint",
+            Mode::Ultra => "ultra",
+            Mode::Dig => "dig",
+            Mode::Master => "master",
+        }
+    }
+
+    // the mode after this one, wraps around
+    pub fn cycle(self) -> Mode {
+        let i = Mode::ALL.iter().position(|&m| m == self).unwrap_or(0);
+        Mode::ALL[(i + 1) % Mode::ALL.len()]
+    }
+
+    // game is finished when this many lines are cleared
+    pub fn goal_lines(self) -> Option<i32> {
+        match self {
+            Mode::Marathon => Some(150),
+            Mode::Sprint => Some(40),
+            Mode::Ultra | Mode::Dig | Mode::Master => None,
+        }
+    }
+
+    // split time is taken every this many lines
+    pub fn split_lines(self) -> Option<i32> {
+        match self {
+            Mode::Sprint => Some(10),
+            _ => None,
+        }
+    }
+
+    // level does not go higher than this
+    pub fn max_level(self) -> Option<i32> {
+        match self {
+            Mode::Marathon => Some(MAX_START_LEVEL),
+            Mode::Master => Some(MASTER_MAX_LEVEL),
+            _ => None,
+        }
+    }
+
+    // game is finished after this many milliseconds of play
+    pub fn time_limit_ms(self) -> Option<u64> {
+        match self {
+            Mode::Ultra => Some(120_000),
+            _ => None,
+        }
+    }
+
+    // gravity in rows per tick that does not change with level
+    pub fn fixed_gravity(self) -> Option<f64> {
+        match self {
+            Mode::Ultra => Some(guideline_gravity(1)),
+            _ => None,
+        }
+    }
+
+    // lock reset used instead of the configured one
+    pub fn fixed_lock_reset(self) -> Option<LockReset> {
+        match self {
+            Mode::Master => Some(LockReset::Step),
+            _ => None,
+        }
+    }
+
+    // multiplier for line clear points used instead of the level,
+    // master levels go up to 999 so points grow with the section
+    pub fn fixed_score_multiplier(self, level: i32) -> Option<i32> {
+        match self {
+            Mode::Master => Some(level / SECTION_LEVELS + 1),
+            _ => None,
+        }
+    }
+
+    // level progression used instead of the configured one
+    pub fn fixed_progression(self) -> Option<LevelProgression> {
+        match self {
+            Mode::Master => Some(LevelProgression::Sections),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,300 @@
+use crate::field::{Cell, GameField};
+use crate::scoring::TSpin;
+use std::rc::Rc;
+
+use crate::piece_set::{PieceDef, PieceSet};
+
+// index of a piece in its PieceSet, only the set hands them out
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct PieceKind(pub(crate) usize);
+
+impl PieceKind {
+    // position in `PieceSet::kinds`
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Piece {
+    // top left corner of the box
+    pub x: i32,
+    pub y: i32,
+    pub rotation: i32,
+    pub kind: PieceKind,
+    pub def: Rc<PieceDef>,
+    // kick used by the last rotation, None if piece moved after it
+    pub last_kick: Option<usize>,
+}
+
+impl Piece {
+    // piece in spawn rotation with its box at the top left corner of the matrix
+    pub fn new(set: &PieceSet, kind: PieceKind) -> Piece {
+        Piece {
+            x: 0,
+            y: 0,
+            rotation: 0,
+            kind,
+            def: set.get(kind).clone(),
+            last_kick: None,
+        }
+    }
+
+    // spawn in the first rotation state with the box horizontally centered and
+    // the lowest row of the piece at the last hidden row, moved by the spawn offset of the piece
+    pub fn spawn(set: &PieceSet, kind: PieceKind, field: &GameField) -> Piece {
+        let mut piece = Piece::new(set, kind);
+        let bottom = piece.def.rotations[0].iter().map(|&(_, y)| y).max().unwrap();
+        piece.x = (field.width() as i32 - piece.def.size.0) / 2 + piece.def.spawn.0;
+        piece.y = field.visible_top() - 1 - bottom + piece.def.spawn.1;
+        piece
+    }
+
+    // return true if moved, false if something blocks move
+    pub fn move_x(&mut self, delta: i32, field: &GameField) -> bool {
+        let mut moved = self.clone();
+        moved.x += delta;
+        moved.last_kick = None;
+        if !moved.fits(field) {
+            return false;
+        }
+        *self = moved;
+        true
+    }
+
+    pub fn is_move_down_awailable(&self, field: &GameField) -> bool {
+        let mut moved = self.clone();
+        moved.force_move_y(1);
+        moved.fits(field)
+    }
+
+    pub fn force_move_y(&mut self, delta: i32) {
+        self.y += delta;
+        self.last_kick = None;
+    }
+
+    pub fn drop_down(&mut self, field: &GameField) {
+        while self.is_move_down_awailable(field) {
+            self.force_move_y(1);
+        }
+    }
+
+    // rotate using kicks of the piece, return true if rotated, false if every kick is blocked
+    pub fn rotate(&mut self, field: &GameField, direction: i32) -> bool {
+        let states = self.def.rotations.len();
+        for (i, &(dx, dy)) in self.def.kicks.kicks(self.rotation, states, direction).iter().enumerate() {
+            let mut rotated = self.clone();
+            rotated.rotation = (self.rotation + direction).rem_euclid(states as i32);
+            rotated.x += dx;
+            rotated.y += dy;
+            rotated.last_kick = Some(i);
+            if rotated.fits(field) {
+                *self = rotated;
+                return true;
+            }
+        }
+        false
+    }
+
+    // T-spin by 3 corner rule, piece must be a T-spin piece with 4 rotation states
+    // and its last action a rotation
+    pub fn t_spin(&self, field: &GameField) -> TSpin {
+        let kick = match self.last_kick {
+            Some(kick) if self.def.t_spin && self.def.rotations.len() == 4 => kick,
+            _ => return TSpin::None,
+        };
+
+        // corners around the cell at the pivot
+        let (cx, cy) = (self.def.pivot.0.floor() as i32, self.def.pivot.1.floor() as i32);
+        let is_blocked = |dx: i32, dy: i32| field.is_blocked(self.x + cx + dx, self.y + cy + dy);
+        let corners = [(-1, -1), (1, -1), (1, 1), (-1, 1)];
+        if corners.iter().filter(|&&(dx, dy)| is_blocked(dx, dy)).count() < 3 {
+            return TSpin::None;
+        }
+
+        // two corners next to the pointing side of T, clockwise from spawn rotation
+        let front = corners[self.rotation as usize];
+        let front_next = corners[(self.rotation as usize + 1) % 4];
+        if (is_blocked(front.0, front.1) && is_blocked(front_next.0, front_next.1)) || self.def.kicks.forces_full_t_spin(kick) {
+            TSpin::Full
+        } else {
+            TSpin::Mini
+        }
+    }
+
+    // true if piece is inside of the matrix and does not overlap laying pieces
+    pub fn fits(&self, field: &GameField) -> bool {
+        self.cells().iter().all(|&(x, y)| !field.is_blocked(x, y))
+    }
+
+    // cells occupied by the piece as (x, y), may be outside of the matrix
+    pub fn cells(&self) -> Vec<(i32, i32)> {
+        self.def.rotations[self.rotation as usize].iter().map(|&(x, y)| (self.x + x, self.y + y)).collect()
+    }
+
+    // lowest row the piece occupies
+    pub fn bottom(&self) -> i32 {
+        self.cells().iter().map(|&(_, y)| y).max().unwrap_or(self.y)
+    }
+
+    pub fn put_on_a_field(&self, field: &mut GameField) {
+        for (x, y) in self.cells() {
+            field.set(x, y, Cell::Locked(self.kind));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn piece_at(set: &PieceSet, name: &str, x: i32, y: i32) -> Piece {
+        let mut piece = Piece::new(set, set.find(name).unwrap());
+        piece.x = x;
+        piece.y = y;
+        piece
+    }
+
+    #[test]
+    fn rotates_in_place_when_free() {
+        let set = PieceSet::tetromino();
+        let field = GameField::default();
+        let mut piece = piece_at(&set, "T", 4, 30);
+        assert!(piece.rotate(&field, 1));
+        assert_eq!((piece.x, piece.y, piece.rotation, piece.last_kick), (4, 30, 1, Some(0)));
+        assert!(piece.rotate(&field, -1));
+        assert_eq!(piece.rotation, 0);
+    }
+
+    #[test]
+    fn takes_first_kick_that_fits() {
+        let set = PieceSet::tetromino();
+        let mut field = GameField::default();
+        let mut piece = piece_at(&set, "T", 4, 30);
+
+        // block a cell of the in place rotation that the first kick, one to the left, leaves free
+        let mut rotated = piece.clone();
+        rotated.rotation = 1;
+        let kicked: Vec<_> = rotated.cells().iter().map(|&(x, y)| (x - 1, y)).collect();
+        let &(x, y) = rotated.cells().iter().find(|cell| !kicked.contains(cell)).unwrap();
+        field.set(x, y, Cell::Garbage);
+
+        assert!(piece.rotate(&field, 1));
+        assert_eq!((piece.x, piece.y, piece.rotation, piece.last_kick), (3, 30, 1, Some(1)));
+    }
+
+    #[test]
+    fn rotation_fails_when_every_kick_is_blocked() {
+        let set = PieceSet::tetromino();
+        let mut field = GameField::default();
+        let mut piece = piece_at(&set, "T", 4, 30);
+        for y in 0..field.height() as i32 {
+            for x in 0..field.width() as i32 {
+                if !piece.cells().contains(&(x, y)) {
+                    field.set(x, y, Cell::Garbage);
+                }
+            }
+        }
+        assert!(!piece.rotate(&field, 1));
+        assert_eq!((piece.x, piece.y, piece.rotation), (4, 30, 0));
+    }
+
+    #[test]
+    fn i_kicks_off_the_left_wall() {
+        let set = PieceSet::tetromino();
+        let field = GameField::default();
+        // vertical I in state L hugging the left wall, its cells are in column 1 of the box
+        let mut piece = piece_at(&set, "I", -1, 30);
+        piece.rotation = 3;
+        assert!(piece.fits(&field));
+        assert!(piece.rotate(&field, 1));
+        // in place the flat I would stick out of the wall, the first kick moves it one right
+        assert_eq!((piece.x, piece.rotation, piece.last_kick), (0, 0, Some(1)));
+    }
+
+    // T in `rotation` just rotated with kick `kick`, blocks in the given corners around its center
+    fn t_in_corners(set: &PieceSet, rotation: i32, kick: usize, corners: &[(i32, i32)]) -> (Piece, GameField) {
+        let mut field = GameField::default();
+        let mut piece = piece_at(set, "T", 4, 30);
+        piece.rotation = rotation;
+        piece.last_kick = Some(kick);
+        for &(dx, dy) in corners {
+            field.set(piece.x + 1 + dx, piece.y + 1 + dy, Cell::Garbage);
+        }
+        assert!(piece.fits(&field));
+        (piece, field)
+    }
+
+    #[test]
+    fn both_front_corners_is_full_t_spin() {
+        let set = PieceSet::tetromino();
+        // pointing down, front corners are the lower ones
+        let (piece, field) = t_in_corners(&set, 2, 0, &[(-1, 1), (1, 1), (-1, -1)]);
+        assert_eq!(piece.t_spin(&field), TSpin::Full);
+        // pointing up, front corners are the upper ones
+        let (piece, field) = t_in_corners(&set, 0, 1, &[(-1, -1), (1, -1), (1, 1)]);
+        assert_eq!(piece.t_spin(&field), TSpin::Full);
+    }
+
+    #[test]
+    fn one_front_corner_is_mini() {
+        let set = PieceSet::tetromino();
+        let (piece, field) = t_in_corners(&set, 2, 0, &[(-1, -1), (1, -1), (1, 1)]);
+        assert_eq!(piece.t_spin(&field), TSpin::Mini);
+    }
+
+    #[test]
+    fn last_jlstz_kick_makes_mini_full() {
+        let set = PieceSet::tetromino();
+        let (piece, field) = t_in_corners(&set, 2, 4, &[(-1, -1), (1, -1), (1, 1)]);
+        assert_eq!(piece.t_spin(&field), TSpin::Full);
+    }
+
+    #[test]
+    fn last_kick_of_other_tables_stays_mini() {
+        let text = include_str!("../assets/pieces/tetromino.ron").replace("kicks: Jlstz,\n            t_spin: true", "kicks: I,\n            t_spin: true");
+        let set = PieceSet::from_ron(&text).unwrap();
+        assert_eq!(set.get(set.find("T").unwrap()).kicks, crate::srs::KickTable::I);
+        let (piece, field) = t_in_corners(&set, 2, 4, &[(-1, -1), (1, -1), (1, 1)]);
+        assert_eq!(piece.t_spin(&field), TSpin::Mini);
+    }
+
+    #[test]
+    fn floor_counts_as_corners() {
+        let set = PieceSet::tetromino();
+        let mut field = GameField::default();
+        // pointing up on the floor, both lower corners are below the field
+        let mut piece = piece_at(&set, "T", 4, field.height() as i32 - 2);
+        piece.last_kick = Some(0);
+        field.set(piece.x, piece.y, Cell::Garbage);
+        assert!(piece.fits(&field));
+        assert_eq!(piece.t_spin(&field), TSpin::Mini);
+    }
+
+    #[test]
+    fn no_t_spin_without_three_corners() {
+        let set = PieceSet::tetromino();
+        let (piece, field) = t_in_corners(&set, 2, 0, &[(-1, 1), (1, 1)]);
+        assert_eq!(piece.t_spin(&field), TSpin::None);
+    }
+
+    #[test]
+    fn no_t_spin_after_moving() {
+        let set = PieceSet::tetromino();
+        let (mut piece, field) = t_in_corners(&set, 2, 0, &[(-1, 1), (1, 1), (-1, -1)]);
+        piece.last_kick = None;
+        assert_eq!(piece.t_spin(&field), TSpin::None);
+    }
+
+    #[test]
+    fn only_t_spin_pieces_spin() {
+        let set = PieceSet::tetromino();
+        let mut field = GameField::default();
+        let mut piece = piece_at(&set, "J", 4, 30);
+        piece.last_kick = Some(0);
+        for (dx, dy) in [(-1, -1), (1, -1), (1, 1), (-1, 1)] {
+            field.set(piece.x + 1 + dx, piece.y + 1 + dy, Cell::Garbage);
+        }
+        assert_eq!(piece.t_spin(&field), TSpin::None);
+    }
+}
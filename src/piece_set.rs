@@ -0,0 +1,236 @@
+// pieces of a game described in RON files, see assets/pieces/tetromino.ron
+
+use std::fs;
+use std::rc::Rc;
+
+use serde::Deserialize;
+
+use crate::piece::PieceKind;
+use crate::srs::KickTable;
+
+const TETROMINO: &str = include_str!("../assets/pieces/tetromino.ron");
+
+// piece as written in the file
+#[derive(Deserialize)]
+#[serde(rename = "Piece")]
+struct PieceFile {
+    name: String,
+    texture: String,
+    #[serde(default)]
+    kicks: KickTable,
+    #[serde(default)]
+    t_spin: bool,
+    #[serde(default)]
+    spawn: (i32, i32),
+    #[serde(default)]
+    pivot: Option<(f32, f32)>,
+    rotations: Vec<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "PieceSet")]
+struct PieceSetFile {
+    pieces: Vec<PieceFile>,
+}
+
+#[derive(Debug)]
+pub struct PieceDef {
+    pub name: String,
+    // file name of the texture in assets
+    pub texture: String,
+    pub kicks: KickTable,
+    // locking it after a rotation into a tight spot is a T-spin
+    pub t_spin: bool,
+    // offset from the default spawn position
+    pub spawn: (i32, i32),
+    // point the piece rotates around in box coordinates, cell (x, y) spans x..x + 1 and y..y + 1
+    pub pivot: (f32, f32),
+    // width and height of the box
+    pub size: (i32, i32),
+    // cells as (x, y) inside of the box for every rotation state, clockwise from spawn
+    pub rotations: Vec<Vec<(i32, i32)>>,
+}
+
+impl PieceDef {
+    fn from_file(piece: PieceFile) -> Result<PieceDef, String> {
+        let mut width = piece.rotations.iter().flatten().map(|row| row.chars().count()).max().unwrap_or(0) as i32;
+        let mut height = piece.rotations.iter().map(|rows| rows.len()).max().unwrap_or(0) as i32;
+        let mut rotations = Vec::new();
+        for rows in &piece.rotations {
+            let mut cells = Vec::new();
+            for (y, row) in rows.iter().enumerate() {
+                for (x, ch) in row.chars().enumerate() {
+                    match ch {
+                        '#' => cells.push((x as i32, y as i32)),
+                        '.' => (),
+                        _ => return Err(format!("piece {}: unknown cell '{}'", piece.name, ch)),
+                    }
+                }
+            }
+            if cells.is_empty() {
+                return Err(format!("piece {}: empty rotation state", piece.name));
+            }
+            rotations.push(cells);
+        }
+        if rotations.is_empty() {
+            return Err(format!("piece {}: no rotation states", piece.name));
+        }
+
+        // with a pivot and only the spawn state given the other three are rotated around the pivot
+        let pivot = piece.pivot.unwrap_or((width as f32 / 2., height as f32 / 2.));
+        if piece.pivot.is_some() && rotations.len() == 1 {
+            for _ in 0..3 {
+                let rotated = rotate_cells(rotations.last().unwrap(), pivot)
+                    .ok_or_else(|| format!("piece {}: pivot must be a center or a corner of a cell", piece.name))?;
+                rotations.push(rotated);
+            }
+        }
+        if rotations.iter().flatten().any(|&(x, y)| x < 0 || y < 0) {
+            return Err(format!("piece {}: rotation around the pivot leaves the box", piece.name));
+        }
+        // box grows to fit the rotated states
+        for &(x, y) in rotations.iter().flatten() {
+            width = width.max(x + 1);
+            height = height.max(y + 1);
+        }
+        if !piece.kicks.supports(rotations.len()) {
+            return Err(format!("piece {}: kicks must have offsets for each of {} rotation states", piece.name, rotations.len()));
+        }
+        Ok(PieceDef {
+            name: piece.name,
+            texture: piece.texture,
+            kicks: piece.kicks,
+            t_spin: piece.t_spin,
+            spawn: piece.spawn,
+            pivot,
+            size: (width, height),
+            rotations,
+        })
+    }
+}
+
+// cells rotated clockwise around the pivot, None if they don't land on the grid
+fn rotate_cells(cells: &[(i32, i32)], (px, py): (f32, f32)) -> Option<Vec<(i32, i32)>> {
+    cells.iter().map(|&(x, y)| {
+        // rotate the cell center, y points down
+        let cx = px - (y as f32 + 0.5 - py) - 0.5;
+        let cy = py + (x as f32 + 0.5 - px) - 0.5;
+        if cx.fract() != 0. || cy.fract() != 0. {
+            return None;
+        }
+        Some((cx as i32, cy as i32))
+    }).collect()
+}
+
+// pieces a game is played with, PieceKind is an index into it
+#[derive(Clone, Debug)]
+pub struct PieceSet {
+    pieces: Vec<Rc<PieceDef>>,
+}
+
+impl PieceSet {
+    pub fn from_ron(text: &str) -> Result<PieceSet, String> {
+        let file: PieceSetFile = ron::from_str(text).map_err(|err| err.to_string())?;
+        if file.pieces.is_empty() {
+            return Err("no pieces".to_string());
+        }
+        let pieces = file.pieces.into_iter().map(|piece| PieceDef::from_file(piece).map(Rc::new)).collect::<Result<_, _>>()?;
+        Ok(PieceSet { pieces })
+    }
+
+    pub fn load(path: &str) -> Result<PieceSet, String> {
+        let text = fs::read_to_string(path).map_err(|err| format!("{}: {}", path, err))?;
+        PieceSet::from_ron(&text).map_err(|err| format!("{}: {}", path, err))
+    }
+
+    // guideline tetrominoes, I, O, T, S, Z, J, L
+    pub fn tetromino() -> PieceSet {
+        PieceSet::from_ron(TETROMINO).unwrap()
+    }
+
+    pub fn len(&self) -> usize {
+        self.pieces.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pieces.is_empty()
+    }
+
+    pub fn kinds(&self) -> Vec<PieceKind> {
+        (0..self.pieces.len()).map(PieceKind).collect()
+    }
+
+    pub fn get(&self, kind: PieceKind) -> &Rc<PieceDef> {
+        &self.pieces[kind.0]
+    }
+
+    // kind of the piece with this name
+    pub fn find(&self, name: &str) -> Option<PieceKind> {
+        self.pieces.iter().position(|piece| piece.name == name).map(PieceKind)
+    }
+}
+
+impl Default for PieceSet {
+    fn default() -> Self {
+        PieceSet::tetromino()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // set with one piece, `fields` go before its rotation states
+    fn one_piece(fields: &str, rotations: &[&[&str]]) -> Result<PieceSet, String> {
+        PieceSet::from_ron(&format!("PieceSet(pieces: [(name: \"A\", texture: \"red.png\", {} rotations: {:?})])", fields, rotations))
+    }
+
+    #[test]
+    fn bundled_sets_load() {
+        let tetromino = PieceSet::tetromino();
+        let names: Vec<_> = tetromino.kinds().iter().map(|&kind| tetromino.get(kind).name.clone()).collect();
+        assert_eq!(names, ["I", "O", "T", "S", "Z", "J", "L"]);
+        assert!(PieceSet::from_ron(include_str!("../assets/pieces/tromino.ron")).is_ok());
+        assert!(PieceSet::from_ron(include_str!("../assets/pieces/pentomino.ron")).is_ok());
+    }
+
+    #[test]
+    fn pivot_generates_rotations() {
+        let set = one_piece("kicks: Jlstz, pivot: Some((1.5, 1.5)),", &[&[".#.", "###"]]).unwrap();
+        let tetromino = PieceSet::tetromino();
+        let generated = set.get(set.kinds()[0]);
+        let written = tetromino.get(tetromino.find("T").unwrap());
+        assert_eq!(generated.rotations.len(), 4);
+        for (generated, written) in generated.rotations.iter().zip(&written.rotations) {
+            let mut generated = generated.clone();
+            let mut written = written.clone();
+            generated.sort();
+            written.sort();
+            assert_eq!(generated, written);
+        }
+        assert_eq!(generated.size, (3, 3));
+    }
+
+    #[test]
+    fn pivot_off_the_grid_is_an_error() {
+        assert!(one_piece("pivot: Some((0.25, 0.5)),", &[&["##"]]).is_err());
+    }
+
+    #[test]
+    fn kicks_must_match_rotation_states() {
+        assert!(one_piece("kicks: Jlstz,", &[&["##"], &["#", "#"]]).is_err());
+    }
+
+    #[test]
+    fn custom_kicks_need_offsets_for_every_state() {
+        assert!(one_piece("kicks: Custom([([(0, 0)], [(0, 0)]), ([(0, 0), (1, 0)], [(0, 0)])]),", &[&["##"], &["#", "#"]]).is_ok());
+        assert!(one_piece("kicks: Custom([([(0, 0)], []), ([(0, 0)], [(0, 0)])]),", &[&["##"], &["#", "#"]]).is_err());
+    }
+
+    #[test]
+    fn unknown_cells_and_empty_sets_are_errors() {
+        assert!(one_piece("", &[&["#x"]]).is_err());
+        assert!(one_piece("", &[&[".."]]).is_err());
+        assert!(PieceSet::from_ron("PieceSet(pieces: [])").is_err());
+    }
+}
@@ -0,0 +1,254 @@
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use crate::piece::PieceKind;
+use crate::piece_set::PieceSet;
+
+// generates the sequence of pieces for a game
+pub trait Randomizer {
+    fn next(&mut self) -> PieceKind;
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RandomizerKind {
+    SevenBag,
+    FourteenBag,
+    Random,
+    NesReroll,
+    TgmHistory,
+}
+
+impl RandomizerKind {
+    pub const ALL: [RandomizerKind; 5] = [
+        RandomizerKind::SevenBag,
+        RandomizerKind::FourteenBag,
+        RandomizerKind::Random,
+        RandomizerKind::NesReroll,
+        RandomizerKind::TgmHistory,
+    ];
+
+    pub fn create(self, set: &PieceSet) -> Box<dyn Randomizer> {
+        let kinds = set.kinds();
+        match self {
+            RandomizerKind::SevenBag => Box::new(Bag::new(kinds, 1)),
+            RandomizerKind::FourteenBag => Box::new(Bag::new(kinds, 2)),
+            RandomizerKind::Random => Box::new(PureRandom::new(kinds)),
+            RandomizerKind::NesReroll => Box::new(NesReroll::new(kinds)),
+            RandomizerKind::TgmHistory => {
+                // S, Z and O are bad first pieces
+                let first = kinds.iter().copied().filter(|&kind| !["S", "Z", "O"].contains(&set.get(kind).name.as_str())).collect();
+                Box::new(TgmHistory::new(kinds, first))
+            },
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RandomizerKind::SevenBag => "7-bag",
+            RandomizerKind::FourteenBag => "14-bag",
+            RandomizerKind::Random => "random",
+            RandomizerKind::NesReroll => "nes",
+            RandomizerKind::TgmHistory => "tgm",
+        }
+    }
+
+    // the kind after this one, wraps around
+    pub fn cycle(self) -> RandomizerKind {
+        let i = RandomizerKind::ALL.iter().position(|&k| k == self).unwrap_or(0);
+        RandomizerKind::ALL[(i + 1) % RandomizerKind::ALL.len()]
+    }
+}
+
+// every piece `copies` times in a shuffled bag, new bag when the old one is empty
+pub struct Bag {
+    rng: StdRng,
+    kinds: Vec<PieceKind>,
+    copies: usize,
+    bag: Vec<PieceKind>,
+}
+
+impl Bag {
+    pub fn new(kinds: Vec<PieceKind>, copies: usize) -> Bag {
+        Bag {
+            rng: StdRng::from_entropy(),
+            kinds,
+            copies,
+            bag: Vec::new(),
+        }
+    }
+}
+
+impl Randomizer for Bag {
+    fn next(&mut self) -> PieceKind {
+        if self.bag.is_empty() {
+            for _ in 0..self.copies {
+                self.bag.extend_from_slice(&self.kinds);
+            }
+            self.bag.shuffle(&mut self.rng);
+        }
+        self.bag.pop().unwrap()
+    }
+}
+
+// every piece independently with equal chance
+pub struct PureRandom {
+    rng: StdRng,
+    kinds: Vec<PieceKind>,
+}
+
+impl PureRandom {
+    pub fn new(kinds: Vec<PieceKind>) -> PureRandom {
+        PureRandom {
+            rng: StdRng::from_entropy(),
+            kinds,
+        }
+    }
+}
+
+impl Randomizer for PureRandom {
+    fn next(&mut self) -> PieceKind {
+        *self.kinds.choose(&mut self.rng).unwrap()
+    }
+}
+
+// roll one of pieces + 1 values, if it is the last one or repeats the previous piece roll once more
+pub struct NesReroll {
+    rng: StdRng,
+    kinds: Vec<PieceKind>,
+    previous: Option<PieceKind>,
+}
+
+impl NesReroll {
+    pub fn new(kinds: Vec<PieceKind>) -> NesReroll {
+        NesReroll {
+            rng: StdRng::from_entropy(),
+            kinds,
+            previous: None,
+        }
+    }
+}
+
+impl Randomizer for NesReroll {
+    fn next(&mut self) -> PieceKind {
+        let roll = self.rng.gen_range(0..self.kinds.len() + 1);
+        let piece = match self.kinds.get(roll) {
+            Some(&piece) if Some(piece) != self.previous => piece,
+            _ => self.kinds[self.rng.gen_range(0..self.kinds.len())],
+        };
+        self.previous = Some(piece);
+        piece
+    }
+}
+
+// remember last 4 pieces and reroll up to 4 times to avoid them,
+// first piece is one of `first_kinds`, any piece if it is empty
+pub struct TgmHistory {
+    rng: StdRng,
+    kinds: Vec<PieceKind>,
+    first_kinds: Vec<PieceKind>,
+    // empty slots until 4 pieces were dealt
+    history: [Option<PieceKind>; 4],
+    first: bool,
+}
+
+impl TgmHistory {
+    const ROLLS: usize = 4;
+
+    pub fn new(kinds: Vec<PieceKind>, first_kinds: Vec<PieceKind>) -> TgmHistory {
+        TgmHistory {
+            rng: StdRng::from_entropy(),
+            kinds,
+            first_kinds,
+            history: [None; 4],
+            first: true,
+        }
+    }
+}
+
+impl Randomizer for TgmHistory {
+    fn next(&mut self) -> PieceKind {
+        let mut piece = *self.kinds.choose(&mut self.rng).unwrap();
+        if self.first {
+            self.first = false;
+            if let Some(&first) = self.first_kinds.choose(&mut self.rng) {
+                piece = first;
+            }
+        } else {
+            for _ in 1..TgmHistory::ROLLS {
+                if !self.history.contains(&Some(piece)) {
+                    break;
+                }
+                piece = *self.kinds.choose(&mut self.rng).unwrap();
+            }
+        }
+        self.history.rotate_right(1);
+        self.history[0] = Some(piece);
+        piece
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn deal(kind: RandomizerKind, set: &PieceSet, count: usize) -> Vec<PieceKind> {
+        let mut randomizer = kind.create(set);
+        (0..count).map(|_| randomizer.next()).collect()
+    }
+
+    fn counts(set: &PieceSet, pieces: &[PieceKind]) -> Vec<usize> {
+        set.kinds().iter().map(|kind| pieces.iter().filter(|&piece| piece == kind).count()).collect()
+    }
+
+    #[test]
+    fn seven_bag_deals_every_piece_once_per_bag() {
+        let set = PieceSet::tetromino();
+        for bag in deal(RandomizerKind::SevenBag, &set, 7 * 50).chunks(7) {
+            assert_eq!(counts(&set, bag), vec![1; 7]);
+        }
+    }
+
+    #[test]
+    fn fourteen_bag_deals_every_piece_twice_per_bag() {
+        let set = PieceSet::tetromino();
+        for bag in deal(RandomizerKind::FourteenBag, &set, 14 * 50).chunks(14) {
+            assert_eq!(counts(&set, bag), vec![2; 7]);
+        }
+    }
+
+    #[test]
+    fn bag_follows_the_piece_set() {
+        let set = PieceSet::from_ron(include_str!("../assets/pieces/tromino.ron")).unwrap();
+        for bag in deal(RandomizerKind::SevenBag, &set, set.len() * 20).chunks(set.len()) {
+            assert_eq!(counts(&set, bag), vec![1; set.len()]);
+        }
+    }
+
+    #[test]
+    fn every_randomizer_deals_pieces_of_the_set() {
+        let set = PieceSet::tetromino();
+        for kind in RandomizerKind::ALL {
+            let pieces = deal(kind, &set, 500);
+            assert!(pieces.iter().all(|piece| piece.index() < set.len()), "{}", kind.name());
+        }
+    }
+
+    #[test]
+    fn tgm_history_never_starts_with_s_z_or_o() {
+        let set = PieceSet::tetromino();
+        for _ in 0..100 {
+            let first = deal(RandomizerKind::TgmHistory, &set, 1)[0];
+            assert!(!["S", "Z", "O"].contains(&set.get(first).name.as_str()));
+        }
+    }
+
+    #[test]
+    fn cycle_visits_every_randomizer() {
+        let mut kind = RandomizerKind::SevenBag;
+        for _ in 0..RandomizerKind::ALL.len() {
+            kind = kind.cycle();
+        }
+        assert_eq!(kind, RandomizerKind::SevenBag);
+    }
+}
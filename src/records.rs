@@ -0,0 +1,96 @@
+// personal bests kept between games in a RON file
+
+use std::collections::BTreeMap;
+use std::fs;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Records {
+    // split times in milliseconds of the fastest sprint for every set of rules, see `sprint_rules`
+    #[serde(default)]
+    pub sprints: BTreeMap<String, Vec<u64>>,
+}
+
+// what a sprint record is kept for, games on other boards, with other pieces
+// or another randomizer are not compared with each other
+pub fn sprint_rules(config: &Config, pieces: &str) -> String {
+    format!("{}x{} {} {}", config.width, config.height, pieces, config.randomizer.name())
+}
+
+impl Records {
+    // no records if the file is missing or broken
+    pub fn load(path: &str) -> Records {
+        fs::read_to_string(path).ok().and_then(|text| ron::from_str(&text).ok()).unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()).map_err(|err| err.to_string())?;
+        fs::write(path, text).map_err(|err| format!("{}: {}", path, err))
+    }
+
+    // splits of the fastest sprint with these rules
+    pub fn sprint(&self, rules: &str) -> Option<&Vec<u64>> {
+        self.sprints.get(rules)
+    }
+
+    // keep the sprint if it is faster than the best one with the same rules, return true if it is
+    pub fn submit_sprint(&mut self, rules: &str, splits: &[u64]) -> bool {
+        let best = match (self.sprints.get(rules), splits.last()) {
+            (_, None) => false,
+            (Some(best), Some(time)) => best.last().is_none_or(|best| time < best),
+            (None, Some(_)) => true,
+        };
+        if best {
+            self.sprints.insert(rules.to_string(), splits.to_vec());
+        }
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::randomizer::RandomizerKind;
+
+    #[test]
+    fn faster_sprint_replaces_the_record() {
+        let mut records = Records::default();
+        assert!(records.submit_sprint("rules", &[10, 20, 30, 40]));
+        assert!(!records.submit_sprint("rules", &[5, 15, 30, 41]));
+        assert!(records.submit_sprint("rules", &[5, 15, 30, 39]));
+        assert_eq!(records.sprint("rules"), Some(&vec![5, 15, 30, 39]));
+        assert!(!records.submit_sprint("rules", &[]));
+    }
+
+    #[test]
+    fn records_are_kept_per_rules() {
+        let standard = Config::default();
+        let narrow = Config { width: 4, ..standard };
+        let random = Config { randomizer: RandomizerKind::Random, ..standard };
+        let rules = [
+            sprint_rules(&standard, "tetromino"),
+            sprint_rules(&narrow, "tetromino"),
+            sprint_rules(&random, "tetromino"),
+            sprint_rules(&standard, "tromino"),
+        ];
+        let mut records = Records::default();
+        assert!(records.submit_sprint(&rules[0], &[100]));
+        for rules in &rules[1..] {
+            assert!(records.sprint(rules).is_none());
+            assert!(records.submit_sprint(rules, &[200]));
+        }
+        assert_eq!(records.sprint(&rules[0]), Some(&vec![100]));
+    }
+
+    #[test]
+    fn records_survive_saving() {
+        let mut records = Records::default();
+        records.submit_sprint("10x20 tetromino 7-bag", &[1, 2, 3, 4]);
+        let text = ron::ser::to_string(&records).unwrap();
+        let loaded: Records = ron::from_str(&text).unwrap();
+        assert_eq!(loaded.sprint("10x20 tetromino 7-bag"), Some(&vec![1, 2, 3, 4]));
+    }
+}
@@ -0,0 +1,272 @@
+// guideline scoring: line clears multiplied by level, drop points, combos, back to back and perfect clears
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TSpin {
+    None,
+    Mini,
+    Full,
+}
+
+// what the locked piece did
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct LineClear {
+    pub lines: i32,
+    pub t_spin: TSpin,
+    // field is empty after the clear
+    pub perfect_clear: bool,
+}
+
+impl LineClear {
+    // clear without T-spin or perfect clear
+    #[cfg(test)]
+    pub(crate) fn plain(lines: i32) -> LineClear {
+        LineClear {
+            lines,
+            t_spin: TSpin::None,
+            perfect_clear: false,
+        }
+    }
+
+    // tetrises and T-spins that clear lines, they chain back to back
+    pub fn is_difficult(&self) -> bool {
+        self.lines == 4 || (self.lines > 0 && self.t_spin != TSpin::None)
+    }
+
+    // name for on screen callout, None for ordinary placement
+    pub fn name(&self) -> Option<String> {
+        let lines = match self.lines {
+            0 => "",
+            1 => "single",
+            2 => "double",
+            3 => "triple",
+            _ => "tetris",
+        };
+        let name = match self.t_spin {
+            TSpin::None if self.lines == 0 => return None,
+            TSpin::None => lines.to_string(),
+            TSpin::Mini => format!("t-spin mini {}", lines),
+            TSpin::Full => format!("t-spin {}", lines),
+        };
+        Some(name.trim_end().to_uppercase())
+    }
+}
+
+// how many clears of every type a game had
+#[derive(Clone, Copy, Default, Debug)]
+pub struct ClearStats {
+    // clears without T-spin by lines, tetris and more are counted as tetris
+    pub lines: [u32; 5],
+    pub t_spin_minis: u32,
+    // full T-spins by lines, zero lines included
+    pub t_spins: [u32; 4],
+    pub perfect_clears: u32,
+}
+
+impl ClearStats {
+    pub fn add(&mut self, clear: LineClear) {
+        let lines = clear.lines.max(0) as usize;
+        match clear.t_spin {
+            TSpin::None => self.lines[lines.min(4)] += 1,
+            TSpin::Mini => self.t_spin_minis += 1,
+            TSpin::Full => self.t_spins[lines.min(3)] += 1,
+        }
+        if clear.perfect_clear {
+            self.perfect_clears += 1;
+        }
+    }
+
+    // counts with names for a results screen
+    pub fn entries(&self) -> [(&'static str, u32); 10] {
+        [
+            ("single", self.lines[1]),
+            ("double", self.lines[2]),
+            ("triple", self.lines[3]),
+            ("tetris", self.lines[4]),
+            ("t-spin mini", self.t_spin_minis),
+            ("t-spin", self.t_spins[0]),
+            ("t-spin single", self.t_spins[1]),
+            ("t-spin double", self.t_spins[2]),
+            ("t-spin triple", self.t_spins[3]),
+            ("perfect clear", self.perfect_clears),
+        ]
+    }
+}
+
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Scoring {
+    score: i32,
+    // consecutive pieces that cleared lines minus one, -1 when the last piece cleared nothing
+    combo: i32,
+    // last line clear was difficult, the next difficult one gets the bonus
+    back_to_back: bool,
+}
+
+impl Scoring {
+    pub fn new() -> Scoring {
+        Scoring {
+            score: 0,
+            combo: -1,
+            back_to_back: false,
+        }
+    }
+
+    pub fn soft_drop(&mut self, cells: i32) {
+        self.score += cells;
+    }
+
+    pub fn hard_drop(&mut self, cells: i32) {
+        self.score += 2 * cells;
+    }
+
+    // piece locked, return points for what it cleared
+    pub fn line_clear(&mut self, clear: LineClear, level: i32) -> i32 {
+        let mut points = match (clear.t_spin, clear.lines) {
+            (TSpin::None, 0) => 0,
+            (TSpin::None, 1) => 100,
+            (TSpin::None, 2) => 300,
+            (TSpin::None, 3) => 500,
+            (TSpin::None, _) => 800,
+            (TSpin::Mini, 0) => 100,
+            (TSpin::Mini, 1) => 200,
+            (TSpin::Mini, _) => 400,
+            (TSpin::Full, 0) => 400,
+            (TSpin::Full, 1) => 800,
+            (TSpin::Full, 2) => 1200,
+            (TSpin::Full, _) => 1600,
+        } * level;
+
+        if clear.lines == 0 {
+            self.combo = -1;
+            self.score += points;
+            return points;
+        }
+
+        let back_to_back = clear.is_difficult() && self.back_to_back;
+        if back_to_back {
+            points = points * 3 / 2;
+        }
+        self.back_to_back = clear.is_difficult();
+
+        if clear.perfect_clear {
+            points += match clear.lines {
+                1 => 800,
+                2 => 1200,
+                3 => 1800,
+                _ if back_to_back => 3200,
+                _ => 2000,
+            } * level;
+        }
+
+        self.combo += 1;
+        points += 50 * self.combo * level;
+
+        self.score += points;
+        points
+    }
+
+    pub fn score(&self) -> i32 {
+        self.score
+    }
+
+    pub fn combo(&self) -> i32 {
+        self.combo
+    }
+
+    pub fn is_back_to_back(&self) -> bool {
+        self.back_to_back
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn t_spin(t_spin: TSpin, lines: i32) -> LineClear {
+        LineClear { lines, t_spin, perfect_clear: false }
+    }
+
+    #[test]
+    fn clears_multiplied_by_level() {
+        for (lines, points) in [(0, 0), (1, 100), (2, 300), (3, 500), (4, 800)] {
+            assert_eq!(Scoring::new().line_clear(LineClear::plain(lines), 1), points);
+            assert_eq!(Scoring::new().line_clear(LineClear::plain(lines), 7), points * 7);
+        }
+    }
+
+    #[test]
+    fn t_spins_multiplied_by_level() {
+        let cases = [
+            (TSpin::Mini, 0, 100),
+            (TSpin::Mini, 1, 200),
+            (TSpin::Mini, 2, 400),
+            (TSpin::Full, 0, 400),
+            (TSpin::Full, 1, 800),
+            (TSpin::Full, 2, 1200),
+            (TSpin::Full, 3, 1600),
+        ];
+        for (kind, lines, points) in cases {
+            assert_eq!(Scoring::new().line_clear(t_spin(kind, lines), 2), points * 2);
+        }
+    }
+
+    #[test]
+    fn drops_score_per_cell() {
+        let mut scoring = Scoring::new();
+        scoring.soft_drop(5);
+        scoring.hard_drop(10);
+        assert_eq!(scoring.score(), 5 + 20);
+    }
+
+    #[test]
+    fn combo_adds_50_per_clear_in_a_row() {
+        let mut scoring = Scoring::new();
+        assert_eq!(scoring.line_clear(LineClear::plain(1), 1), 100);
+        assert_eq!(scoring.combo(), 0);
+        assert_eq!(scoring.line_clear(LineClear::plain(1), 1), 150);
+        assert_eq!(scoring.line_clear(LineClear::plain(2), 3), (300 + 100) * 3);
+        // piece without lines ends the combo
+        assert_eq!(scoring.line_clear(LineClear::plain(0), 1), 0);
+        assert_eq!(scoring.combo(), -1);
+        assert_eq!(scoring.line_clear(LineClear::plain(1), 1), 100);
+    }
+
+    #[test]
+    fn back_to_back_difficult_clears_get_half_more() {
+        let mut scoring = Scoring::new();
+        assert_eq!(scoring.line_clear(LineClear::plain(4), 1), 800);
+        assert!(scoring.is_back_to_back());
+        scoring.line_clear(LineClear::plain(0), 1);
+        // placing without a clear keeps the chain, tetris after tetris
+        assert_eq!(scoring.line_clear(LineClear::plain(4), 1), 1200);
+        scoring.line_clear(LineClear::plain(0), 1);
+        assert_eq!(scoring.line_clear(t_spin(TSpin::Full, 2), 1), 1800);
+    }
+
+    #[test]
+    fn easy_clear_breaks_back_to_back() {
+        let mut scoring = Scoring::new();
+        scoring.line_clear(LineClear::plain(4), 1);
+        scoring.line_clear(LineClear::plain(0), 1);
+        scoring.line_clear(LineClear::plain(1), 1);
+        assert!(!scoring.is_back_to_back());
+        scoring.line_clear(LineClear::plain(0), 1);
+        assert_eq!(scoring.line_clear(LineClear::plain(4), 1), 800);
+    }
+
+    #[test]
+    fn t_spin_without_lines_keeps_back_to_back() {
+        let mut scoring = Scoring::new();
+        scoring.line_clear(LineClear::plain(4), 1);
+        scoring.line_clear(t_spin(TSpin::Full, 0), 1);
+        assert!(scoring.is_back_to_back());
+    }
+
+    #[test]
+    fn callout_names() {
+        assert_eq!(LineClear::plain(0).name(), None);
+        assert_eq!(LineClear::plain(4).name().unwrap(), "TETRIS");
+        assert_eq!(t_spin(TSpin::Full, 2).name().unwrap(), "T-SPIN DOUBLE");
+        assert_eq!(t_spin(TSpin::Mini, 0).name().unwrap(), "T-SPIN MINI");
+        assert_eq!(t_spin(TSpin::Full, 0).name().unwrap(), "T-SPIN");
+    }
+}
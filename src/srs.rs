@@ -0,0 +1,155 @@
+// Super Rotation System wall kick data
+//
+// offsets are (x, y) with y pointing down, like the game field,
+// rotation states are 0 = spawn, 1 = R, 2 = 2, 3 = L
+
+use serde::Deserialize;
+
+type Kicks = [(i32, i32); 5];
+type Offsets = Vec<(i32, i32)>;
+
+// index of the last JLSTZ kick, T-spin that needed it always counts as full
+const LAST_KICK: usize = 4;
+
+// J, L, S, T, Z pieces
+const JLSTZ_KICKS: [[Kicks; 2]; 4] = [
+    // from 0: to R, to L
+    [
+        [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)],
+        [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)],
+    ],
+    // from R: to 2, to 0
+    [
+        [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)],
+        [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)],
+    ],
+    // from 2: to L, to R
+    [
+        [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)],
+        [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)],
+    ],
+    // from L: to 0, to 2
+    [
+        [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)],
+        [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)],
+    ],
+];
+
+// I piece
+const I_KICKS: [[Kicks; 2]; 4] = [
+    // from 0: to R, to L
+    [
+        [(0, 0), (-2, 0), (1, 0), (-2, 1), (1, -2)],
+        [(0, 0), (-1, 0), (2, 0), (-1, -2), (2, 1)],
+    ],
+    // from R: to 2, to 0
+    [
+        [(0, 0), (-1, 0), (2, 0), (-1, -2), (2, 1)],
+        [(0, 0), (2, 0), (-1, 0), (2, -1), (-1, 2)],
+    ],
+    // from 2: to L, to R
+    [
+        [(0, 0), (2, 0), (-1, 0), (2, -1), (-1, 2)],
+        [(0, 0), (1, 0), (-2, 0), (1, 2), (-2, -1)],
+    ],
+    // from L: to 0, to 2
+    [
+        [(0, 0), (1, 0), (-2, 0), (1, 2), (-2, -1)],
+        [(0, 0), (-2, 0), (1, 0), (-2, 1), (1, -2)],
+    ],
+];
+
+// pieces without kicks only rotate in place
+const NO_KICKS: [(i32, i32); 1] = [(0, 0)];
+
+// wall kicks of a piece, as named in piece set files
+#[derive(Clone, PartialEq, Eq, Debug, Default, Deserialize)]
+pub enum KickTable {
+    #[default]
+    None,
+    Jlstz,
+    I,
+    // offsets for every rotation state, clockwise ones first, then counter clockwise ones
+    Custom(Vec<(Offsets, Offsets)>),
+}
+
+impl KickTable {
+    // true if the table fits a piece with `states` rotation states
+    pub fn supports(&self, states: usize) -> bool {
+        match self {
+            KickTable::None => true,
+            KickTable::Jlstz | KickTable::I => states == 4,
+            // a state without offsets could never be left
+            KickTable::Custom(kicks) => kicks.len() == states && kicks.iter().all(|(cw, ccw)| !cw.is_empty() && !ccw.is_empty()),
+        }
+    }
+
+    // T-spin that needed kick `kick` of this table counts as full even with one front corner,
+    // only the JLSTZ table has such a kick
+    pub fn forces_full_t_spin(&self, kick: usize) -> bool {
+        *self == KickTable::Jlstz && kick == LAST_KICK
+    }
+
+    // offsets to test in order when rotating from state `from` of `states`,
+    // `direction` is 1 for clockwise and -1 for counter clockwise
+    pub fn kicks(&self, from: i32, states: usize, direction: i32) -> &[(i32, i32)] {
+        let from = from.rem_euclid(states as i32) as usize;
+        let clockwise = if direction > 0 { 0 } else { 1 };
+        match self {
+            KickTable::None => &NO_KICKS,
+            KickTable::Jlstz => &JLSTZ_KICKS[from][clockwise],
+            KickTable::I => &I_KICKS[from][clockwise],
+            KickTable::Custom(kicks) if clockwise == 0 => &kicks[from].0,
+            KickTable::Custom(kicks) => &kicks[from].1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_kick_rotates_in_place() {
+        for table in [KickTable::None, KickTable::Jlstz, KickTable::I] {
+            for from in 0..4 {
+                for direction in [1, -1] {
+                    assert_eq!(table.kicks(from, 4, direction)[0], (0, 0));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn jlstz_kicks_in_guideline_order() {
+        // y points down, so guideline (-1, +1) is (-1, -1) here
+        assert_eq!(KickTable::Jlstz.kicks(0, 4, 1), &[(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)]);
+        assert_eq!(KickTable::Jlstz.kicks(0, 4, -1), &[(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)]);
+        assert_eq!(KickTable::Jlstz.kicks(3, 4, 1), &[(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)]);
+    }
+
+    #[test]
+    fn i_kicks_in_guideline_order() {
+        assert_eq!(KickTable::I.kicks(0, 4, 1), &[(0, 0), (-2, 0), (1, 0), (-2, 1), (1, -2)]);
+        assert_eq!(KickTable::I.kicks(1, 4, -1), &[(0, 0), (2, 0), (-1, 0), (2, -1), (-1, 2)]);
+    }
+
+    #[test]
+    fn rotating_back_mirrors_kicks() {
+        for table in [KickTable::Jlstz, KickTable::I] {
+            for from in 0..4 {
+                let there = table.kicks(from, 4, 1);
+                let back = table.kicks(from + 1, 4, -1);
+                for (&(x, y), &(back_x, back_y)) in there.iter().zip(back) {
+                    assert_eq!((x, y), (-back_x, -back_y), "{:?} from {}", table, from);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn from_state_wraps_around() {
+        assert_eq!(KickTable::Jlstz.kicks(4, 4, 1), KickTable::Jlstz.kicks(0, 4, 1));
+        assert_eq!(KickTable::Jlstz.kicks(-1, 4, 1), KickTable::Jlstz.kicks(3, 4, 1));
+    }
+}